@@ -0,0 +1,201 @@
+//! The demo shown behind the title screen when it is left idle: a paddle
+//! on autopilot breaking a random wall of bricks.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use ggez::graphics::{Color, Rect};
+
+use cgmath::Vector2;
+
+use engine::juice::Juice;
+use engine::render::Renderer;
+
+use super::{VIRTUAL_HEIGHT, VIRTUAL_WIDTH};
+
+const BRICK_WIDTH: f32 = 32.0;
+const BRICK_HEIGHT: f32 = 16.0;
+const PADDLE_WIDTH: f32 = 64.0;
+const PADDLE_HEIGHT: f32 = 16.0;
+const PADDLE_SPEED: f32 = 200.0;
+const BALL_SIZE: f32 = 8.0;
+
+// One color per row, from the top.
+const ROWS: [Color; 5] = [
+    Color::new(99.0 / 255.0, 155.0 / 255.0, 1.0, 1.0),
+    Color::new(106.0 / 255.0, 190.0 / 255.0, 47.0 / 255.0, 1.0),
+    Color::new(217.0 / 255.0, 87.0 / 255.0, 99.0 / 255.0, 1.0),
+    Color::new(215.0 / 255.0, 123.0 / 255.0, 186.0 / 255.0, 1.0),
+    Color::new(251.0 / 255.0, 242.0 / 255.0, 54.0 / 255.0, 1.0),
+];
+const PADDLE: Color = Color::new(99.0 / 255.0, 155.0 / 255.0, 1.0, 1.0);
+
+struct Brick {
+    rect: Rect,
+    color: Color,
+}
+
+pub struct Demo {
+    rng: StdRng,
+    bricks: Vec<Brick>,
+    paddle: Rect,
+    ball: Rect,
+    velocity: (f32, f32),
+    /// Seconds since the demo started.
+    pub time: f32,
+    // Balls the autopilot missed.
+    missed: u32,
+}
+
+impl Demo {
+    pub fn new(seed: u64) -> Demo {
+        let paddle = Rect::new(
+            (VIRTUAL_WIDTH - PADDLE_WIDTH) / 2.0,
+            VIRTUAL_HEIGHT - 32.0,
+            PADDLE_WIDTH,
+            PADDLE_HEIGHT,
+        );
+        let mut demo = Demo {
+            rng: StdRng::seed_from_u64(seed),
+            bricks: Vec::new(),
+            paddle,
+            ball: Rect::new(0.0, 0.0, BALL_SIZE, BALL_SIZE),
+            velocity: (0.0, 0.0),
+            time: 0.0,
+            missed: 0,
+        };
+        demo.new_level();
+        demo
+    }
+
+    // A few rows of bricks with gaps, centered at the top.
+    fn new_level(&mut self) {
+        let rows = self.rng.gen_range(2..=ROWS.len());
+        let columns = self.rng.gen_range(7..=12);
+        let left = (VIRTUAL_WIDTH - columns as f32 * BRICK_WIDTH) / 2.0;
+        self.bricks.clear();
+        for (row, &color) in ROWS.iter().enumerate().take(rows) {
+            for column in 0..columns {
+                if self.rng.gen_bool(0.2) {
+                    continue;
+                }
+                let x = left + column as f32 * BRICK_WIDTH;
+                let y = 16.0 + row as f32 * BRICK_HEIGHT;
+                self.bricks.push(Brick {
+                    rect: Rect::new(x, y, BRICK_WIDTH, BRICK_HEIGHT),
+                    color,
+                });
+            }
+        }
+        self.serve();
+    }
+
+    fn serve(&mut self) {
+        self.ball.x = self.paddle.x + (PADDLE_WIDTH - BALL_SIZE) / 2.0;
+        self.ball.y = self.paddle.y - BALL_SIZE;
+        self.velocity = (
+            self.rng.gen_range(-200.0..200.0),
+            -self.rng.gen_range(50.0..60.0),
+        );
+    }
+
+    pub fn update(&mut self, dt: f32, juice: &mut Juice) {
+        self.time += dt;
+
+        // The autopilot keeps the middle of the paddle under the ball.
+        let target = self.ball.x + BALL_SIZE / 2.0 - PADDLE_WIDTH / 2.0;
+        let step = (target - self.paddle.x).clamp(-PADDLE_SPEED * dt, PADDLE_SPEED * dt);
+        self.paddle.x = (self.paddle.x + step).clamp(0.0, VIRTUAL_WIDTH - PADDLE_WIDTH);
+
+        self.ball.x += self.velocity.0 * dt;
+        self.ball.y += self.velocity.1 * dt;
+
+        juice.trail(0, self.ball);
+
+        if self.ball.x <= 0.0 {
+            self.ball.x = 0.0;
+            self.velocity.0 = self.velocity.0.abs();
+            juice.squash(0, Vector2::new(1.0, 0.0));
+        } else if self.ball.right() >= VIRTUAL_WIDTH {
+            self.ball.x = VIRTUAL_WIDTH - BALL_SIZE;
+            self.velocity.0 = -self.velocity.0.abs();
+            juice.squash(0, Vector2::new(-1.0, 0.0));
+        }
+        if self.ball.y <= 0.0 {
+            self.ball.y = 0.0;
+            self.velocity.1 = self.velocity.1.abs();
+            juice.squash(0, Vector2::new(0.0, 1.0));
+        }
+
+        if self.velocity.1 > 0.0 && self.ball.overlaps(&self.paddle) {
+            self.ball.y = self.paddle.y - BALL_SIZE;
+            self.velocity.1 = -self.velocity.1;
+            juice.shake(self.velocity.0.hypot(self.velocity.1));
+            juice.flash(0);
+            juice.squash(0, Vector2::new(0.0, -1.0));
+            // Further from the middle, more sideways.
+            let offset = self.ball.center().x - self.paddle.center().x;
+            self.velocity.0 = 50.0_f32.copysign(offset) + 8.0 * offset;
+        }
+
+        if let Some(i) = self.bricks.iter().position(|b| b.rect.overlaps(&self.ball)) {
+            let brick = self.bricks.swap_remove(i).rect;
+            // Bounce on the side it went the least into.
+            let x = (self.ball.right() - brick.x).min(brick.right() - self.ball.x);
+            let y = (self.ball.bottom() - brick.y).min(brick.bottom() - self.ball.y);
+            if x < y {
+                self.velocity.0 = -self.velocity.0;
+                juice.squash(0, Vector2::new(1.0, 0.0));
+            } else {
+                self.velocity.1 = -self.velocity.1;
+                juice.squash(0, Vector2::new(0.0, 1.0));
+            }
+            juice.shake(self.velocity.0.hypot(self.velocity.1) / 2.0);
+            // A little faster each time, up to a point.
+            self.velocity.1 = (self.velocity.1 * 1.02).clamp(-150.0, 150.0);
+        }
+
+        if self.bricks.is_empty() {
+            juice.clear();
+            self.new_level();
+        } else if self.ball.y > VIRTUAL_HEIGHT {
+            self.missed += 1;
+            log::debug!("the demo missed {} balls", self.missed);
+            juice.clear();
+            self.serve();
+        }
+    }
+
+    pub fn render(&self, renderer: &mut dyn Renderer, juice: &Juice) {
+        for brick in &self.bricks {
+            renderer.rect(brick.rect, brick.color);
+        }
+        renderer.rect(self.paddle, juice.tint(0, PADDLE));
+        juice.render_trails(renderer, Color::WHITE);
+        // Squashed around its middle.
+        let scale = juice.scale(0);
+        let (w, h) = (BALL_SIZE * scale.x, BALL_SIZE * scale.y);
+        let center = self.ball.center();
+        let ball = Rect::new(center.x - w / 2.0, center.y - h / 2.0, w, h);
+        renderer.rect(ball, Color::WHITE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use engine::juice::Juice;
+
+    use super::Demo;
+
+    #[test]
+    fn the_autopilot_breaks_bricks_without_missing() {
+        let mut demo = Demo::new(7);
+        let mut juice = Juice::new();
+        let bricks = demo.bricks.len();
+        for _ in 0..60 * 60 {
+            demo.update(1.0 / 60.0, &mut juice);
+        }
+        assert_eq!(demo.missed, 0);
+        assert!(demo.bricks.len() < bricks);
+    }
+}
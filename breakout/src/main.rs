@@ -1,21 +1,48 @@
-use std::collections::HashMap;
-use std::collections::HashSet;
-use std::path;
+use std::time::{Duration, Instant};
 
-use ggez::audio;
-use ggez::event::{KeyCode, KeyMods};
-use ggez::timer;
+use ggez::event::MouseButton;
+use ggez::input::keyboard::{KeyCode, KeyInput};
 use ggez::Context;
 use ggez::GameResult;
 
 use cgmath::{Point2, Vector2};
+use serde::Serialize;
 
+use engine::assets::{Assets, Fonts, Resources};
+use engine::audio::AudioManager;
+use engine::cli::{Flag, Options};
+use engine::console::{self, Command, Console};
+use engine::debug::{Info, Overlay};
+use engine::input::Input;
+use engine::juice::Juice;
+use engine::loading::{Launcher, Reload};
+use engine::logging;
+use engine::render::Renderer;
+use engine::settings::Settings;
+use engine::telemetry::Telemetry;
+use engine::text::TextCache;
+use engine::viewport::Screen;
+
+mod demo;
 mod state;
 
-const WIDTH: f32 = 1280.0;
-const HEIGHT: f32 = 720.0;
+const GAME_ID: &str = "breakout";
+
+const VIRTUAL_WIDTH: f32 = 432.0;
+const VIRTUAL_HEIGHT: f32 = 243.0;
+
+const FLAGS: [Flag; 7] = [
+    Flag::Window,
+    Flag::Size,
+    Flag::NoAudio,
+    Flag::State(&["start", "highscores", "options"]),
+    Flag::Resources,
+    Flag::Log,
+    Flag::Telemetry,
+];
 
-enum SoundKind {
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SoundKind {
     PaddleHit,
     Score,
     WallHit,
@@ -29,38 +56,31 @@ enum SoundKind {
     Recover,
     HighScore,
     Pause,
-    Music,
 }
 
-impl SoundKind {
-    fn to_string(self) -> String {
-        match self {
-            SoundKind::PaddleHit => "PaddleHit".to_string(),
-            SoundKind::Score => "Score".to_string(),
-            SoundKind::WallHit => "WallHit".to_string(),
-            SoundKind::Confirm => "Confirm".to_string(),
-            SoundKind::Select => "Select".to_string(),
-            SoundKind::NoSelect => "NoSelect".to_string(),
-            SoundKind::BrickHit1 => "BrickHit1".to_string(),
-            SoundKind::BrickHit2 => "BrickHit2".to_string(),
-            SoundKind::Hurt => "Hurt".to_string(),
-            SoundKind::Victory => "Victory".to_string(),
-            SoundKind::Recover => "Recover".to_string(),
-            SoundKind::HighScore => "HighScore".to_string(),
-            SoundKind::Pause => "Pause".to_string(),
-            SoundKind::Music => "Music".to_string(),
-        }
-    }
-}
+// The name of each sound in the asset manifest.
+const SOUNDS: [(SoundKind, &str); 13] = [
+    (SoundKind::PaddleHit, "paddle_hit"),
+    (SoundKind::Score, "score"),
+    (SoundKind::WallHit, "wall_hit"),
+    (SoundKind::Confirm, "confirm"),
+    (SoundKind::Select, "select"),
+    (SoundKind::NoSelect, "no_select"),
+    (SoundKind::BrickHit1, "brick_hit_1"),
+    (SoundKind::BrickHit2, "brick_hit_2"),
+    (SoundKind::Hurt, "hurt"),
+    (SoundKind::Victory, "victory"),
+    (SoundKind::Recover, "recover"),
+    (SoundKind::HighScore, "high_score"),
+    (SoundKind::Pause, "pause"),
+];
 
-type Sounds = HashMap<String, audio::Source>;
-type Keys = HashSet<KeyCode>;
-
-pub struct Fonts {
-    font: ggez::graphics::Font,
-    small: f32,
-    medium: f32,
-    large: f32,
+// What the telemetry of a session records.
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    Session,
+    State { name: String },
 }
 
 pub struct HighScore {
@@ -76,166 +96,323 @@ pub struct Images {
     // ['particle'] = love.graphics.newImage('graphics/particle.png')
 }
 
-pub struct GlobalState {
-    sounds: Sounds,
+// Everything the states share.
+pub struct GameData {
+    audio: AudioManager<SoundKind>,
+    juice: Juice,
+    settings: Settings,
     fonts: Fonts,
-    state_machine: state::StateMachine,
     high_scores: Vec<HighScore>,
-    keys_pressed: Keys,
+    input: Input,
+    telemetry: Telemetry,
+}
+
+#[cfg(test)]
+impl GameData {
+    // No sound, no window: enough for the states to be rendered in tests.
+    fn headless(high_scores: Vec<HighScore>) -> GameData {
+        GameData {
+            audio: AudioManager::new(),
+            juice: Juice::new(),
+            settings: Settings::default(),
+            fonts: Fonts {
+                font: "breakout".to_string(),
+                small: 8.0,
+                medium: 16.0,
+                large: 32.0,
+            },
+            high_scores,
+            input: Input::new(),
+            telemetry: Telemetry::disabled(),
+        }
+    }
+}
+
+const COMMANDS: [Command<GlobalState>; 1] = [Command {
+    name: "state",
+    usage: "state start|highscores|options",
+    run: state_command,
+}];
+
+fn state_command(global: &mut GlobalState, args: &[&str]) -> Result<String, String> {
+    let key = args.first().copied().unwrap_or("");
+    // Only the states that were added can be entered.
+    let kind = match state::StateKind::from_key(key) {
+        Some(kind) if global.state_machine.states.contains_key(key) => kind,
+        _ => return Err(format!("usage: {}", COMMANDS[0].usage)),
+    };
+    global.state_machine.change(kind);
+    Ok(format!("state is {}", key))
+}
+
+pub struct GlobalState {
+    data: GameData,
+    state_machine: state::StateMachine,
     images: Images,
+    screen: Screen,
+    text_cache: TextCache,
+    overlay: Overlay,
+    console: Console,
 }
 
 impl GlobalState {
-    fn new(ctx: &mut Context) -> GameResult<GlobalState> {
-        let mut sounds = HashMap::new();
-        sounds.insert(
-            SoundKind::PaddleHit.to_string(),
-            audio::Source::new(ctx, "/sounds/paddle_hit.wav")?,
-        );
-        sounds.insert(
-            SoundKind::Score.to_string(),
-            audio::Source::new(ctx, "/sounds/score.wav")?,
-        );
-        sounds.insert(
-            SoundKind::WallHit.to_string(),
-            audio::Source::new(ctx, "/sounds/wall_hit.wav")?,
-        );
-        sounds.insert(
-            SoundKind::Confirm.to_string(),
-            audio::Source::new(ctx, "/sounds/confirm.wav")?,
-        );
-        sounds.insert(
-            SoundKind::Select.to_string(),
-            audio::Source::new(ctx, "/sounds/select.wav")?,
-        );
-        sounds.insert(
-            SoundKind::NoSelect.to_string(),
-            audio::Source::new(ctx, "/sounds/no-select.wav")?,
-        );
-        sounds.insert(
-            SoundKind::BrickHit1.to_string(),
-            audio::Source::new(ctx, "/sounds/brick-hit-1.wav")?,
-        );
-        sounds.insert(
-            SoundKind::BrickHit2.to_string(),
-            audio::Source::new(ctx, "/sounds/brick-hit-2.wav")?,
-        );
-        sounds.insert(
-            SoundKind::Hurt.to_string(),
-            audio::Source::new(ctx, "/sounds/hurt.wav")?,
-        );
-        sounds.insert(
-            SoundKind::Victory.to_string(),
-            audio::Source::new(ctx, "/sounds/victory.wav")?,
-        );
-        sounds.insert(
-            SoundKind::Recover.to_string(),
-            audio::Source::new(ctx, "/sounds/recover.wav")?,
-        );
-        sounds.insert(
-            SoundKind::HighScore.to_string(),
-            audio::Source::new(ctx, "/sounds/high_score.wav")?,
-        );
-        sounds.insert(
-            SoundKind::Pause.to_string(),
-            audio::Source::new(ctx, "/sounds/pause.wav")?,
-        );
-        sounds.insert(
-            SoundKind::Music.to_string(),
-            audio::Source::new(ctx, "/sounds/music.wav")?,
-        );
+    fn new(
+        ctx: &mut Context,
+        settings: Settings,
+        options: &Options,
+        assets: &Assets,
+    ) -> GameResult<GlobalState> {
+        let mut audio = AudioManager::new();
+        audio.apply_settings(&settings);
+        if options.no_audio {
+            audio.mute();
+        }
+        let mut juice = Juice::new();
+        juice.apply_settings(&settings);
+        for &(kind, name) in SOUNDS.iter() {
+            audio.add(ctx, kind, name, assets.sound(name)?);
+        }
+        if let Some(music) = assets.music() {
+            audio.add_music(ctx, music);
+            audio.play_music(ctx, Duration::from_secs(2));
+        }
 
-        let font = ggez::graphics::Font::new(ctx, "/fonts/font.ttf")?;
-        let fonts = Fonts {
-            font,
-            small: 16.0,
-            medium: 24.0,
-            large: 48.0,
-        };
+        let fonts = assets.fonts("breakout", [8.0, 16.0, 32.0])?;
+        let text_cache = TextCache::new(&fonts);
+        let overlay = Overlay::new(&fonts);
 
-        let mut background = ggez::graphics::Image::new(ctx, "/graphics/background.png")?;
-        background.set_filter(ggez::graphics::FilterMode::Nearest);
+        let background = assets.image("background")?;
         let images = Images { background };
 
+        let screen = Screen::new(ctx, VIRTUAL_WIDTH, VIRTUAL_HEIGHT, true)?;
+
         let mut states = state::StateMachine::new();
         let start_state = state::StartState::new(&fonts);
         let high_score_state = state::HighScoreState::new(&fonts);
-        states
-            .states
-            .insert("start".to_string(), Box::new(start_state));
-        states
-            .states
-            .insert("highscores".to_string(), Box::new(high_score_state));
+        let options_state = state::OptionsState::new(&fonts);
+        states.add("start", Box::new(start_state));
+        states.add("highscores", Box::new(high_score_state));
+        states.add("options", Box::new(options_state));
 
-        // switch to start screen
-        states.change(state::StateKind::Start);
+        // switch to start screen, unless asked for another one
+        let first = options
+            .state
+            .as_deref()
+            .and_then(state::StateKind::from_key);
+        states.change(first.unwrap_or(state::StateKind::Start));
 
-        let state = GlobalState {
+        let mut telemetry = Telemetry::disabled();
+        if options.telemetry {
+            telemetry = Telemetry::start(GAME_ID);
+            telemetry.record(&Event::Session);
+        }
+
+        let data = GameData {
+            audio,
+            juice,
+            settings,
             fonts,
-            images,
-            sounds,
-            state_machine: states,
             high_scores: vec![],
-            keys_pressed: HashSet::new(),
+            input: Input::new(),
+            telemetry,
+        };
+        let state = GlobalState {
+            data,
+            state_machine: states,
+            images,
+            screen,
+            text_cache,
+            overlay,
+            console: Console::new(),
         };
         Ok(state)
     }
+}
 
-    fn display_fps(&mut self, ctx: &mut Context) -> GameResult<()> {
-        let color = ggez::graphics::Color::new(0.0, 1.0, 0.0, 1.0);
-        let fps = format!("FPS: {:.2}", timer::fps(ctx));
-        let text = ggez::graphics::Text::new((fps, self.fonts.font, self.fonts.small));
-        let pos = Point2::new(30.0, 30.0);
-        ggez::graphics::draw(ctx, &text, (pos, color))
+impl Reload for GlobalState {
+    fn reload(&mut self, ctx: &mut Context, assets: &Assets) -> GameResult<()> {
+        let audio = &mut self.data.audio;
+        for &(kind, name) in SOUNDS.iter() {
+            if let Ok(data) = assets.sound(name) {
+                audio.add(ctx, kind, name, data);
+            }
+        }
+        if let Some(music) = assets.music() {
+            audio.add_music(ctx, music);
+            audio.play_music(ctx, Duration::ZERO);
+        }
+        if let Ok(background) = assets.image("background") {
+            self.images.background = background;
+        }
+        // Fonts are looked up by name when drawn, so a reloaded font shows
+        // right away.
+        Ok(())
     }
 }
 
 impl ggez::event::EventHandler<ggez::GameError> for GlobalState {
     fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
-        self.state_machine
-            .update(&mut self.sounds, &self.keys_pressed, ctx)?;
-        self.keys_pressed = HashSet::new();
+        let start = Instant::now();
+        self.data.audio.update(ctx);
+        let before = self.state_machine.current().map(str::to_string);
+        self.state_machine.update(&mut self.data, ctx)?;
+        self.data.juice.update(ctx.time.delta().as_secs_f32());
+        if let Some(name) = self.state_machine.current() {
+            if before.as_deref() != Some(name) {
+                let name = name.to_string();
+                self.data.telemetry.record(&Event::State { name });
+            }
+        }
+        self.data.input.clear();
+        self.overlay.record_update(start.elapsed());
         Ok(())
     }
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
-        ggez::graphics::clear(ctx, [0.0, 0.0, 0.0, 1.0].into());
+        let start = Instant::now();
+        let fps = ctx.time.fps();
+        let mut frame = self.screen.begin(ctx, ggez::graphics::Color::BLACK)?;
         // ggez::graphics::clear(ctx, [40.0 / 255.0, 45.0 / 255.0, 52.0 / 255.0, 1.0].into());
         let dest = Point2::new(0.0, 0.0);
-        let size = self.images.background.dimensions();
-        let scale = Vector2::new(WIDTH / (size.w - 1.0), HEIGHT / (size.h - 1.0));
-        let param = ggez::graphics::DrawParam::new().dest(dest).scale(scale);
-        ggez::graphics::draw(ctx, &self.images.background, param)?;
-
-        self.state_machine.render(self, ctx)?;
-        self.display_fps(ctx)?;
-        ggez::graphics::present(ctx)
+        let background = &self.images.background;
+        let scale = Vector2::new(
+            VIRTUAL_WIDTH / (background.width() as f32 - 1.0),
+            VIRTUAL_HEIGHT / (background.height() as f32 - 1.0),
+        );
+        frame.image(background, dest, scale);
+
+        self.state_machine.render(&self.data, &mut frame)?;
+        if self.data.settings.show_fps {
+            let (cache, fonts) = (&mut self.text_cache, &self.data.fonts);
+            let pos = Point2::new(5.0, 5.0);
+            engine::debug::display_fps(&mut frame, cache, fonts, fps, pos);
+        }
+
+        // No state keeps entities of its own yet.
+        let info = Info {
+            state: self.state_machine.current().unwrap_or("none"),
+            entities: 0,
+            sounds: self.data.audio.playing(),
+        };
+        let panel = ggez::graphics::Rect::new(5.0, VIRTUAL_HEIGHT - 75.0, 200.0, 70.0);
+        let fonts = &self.data.fonts;
+        self.overlay.render(&mut frame, fonts, &info, &[], panel);
+        let area = ggez::graphics::Rect::new(0.0, 0.0, VIRTUAL_WIDTH, VIRTUAL_HEIGHT / 2.0);
+        self.console.render(&mut frame, fonts, area);
+
+        self.screen.shake = self.data.juice.offset();
+        self.screen.end(frame)?;
+        self.overlay.record_draw(start.elapsed());
+        Ok(())
+    }
+
+    fn resize_event(&mut self, _ctx: &mut Context, width: f32, height: f32) -> GameResult<()> {
+        self.screen.resize(width, height);
+        Ok(())
     }
 
-    fn key_down_event(&mut self, _ctx: &mut Context, key: KeyCode, _mods: KeyMods, _repeat: bool) {
-        let _ = self.keys_pressed.insert(key);
+    fn key_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        input: KeyInput,
+        _repeat: bool,
+    ) -> GameResult<()> {
+        let keycode = match input.keycode {
+            Some(keycode) => keycode,
+            None => return Ok(()),
+        };
+        if keycode == console::TOGGLE {
+            self.console.toggle();
+        } else if self.console.open {
+            if let Some(line) = self.console.key(keycode, &COMMANDS) {
+                let result = console::execute(&COMMANDS, self, &line);
+                self.console.print(result);
+            }
+        } else if keycode == KeyCode::F3 {
+            self.overlay.toggle();
+        } else {
+            let _ = self.data.input.keys.insert(keycode);
+        }
+        Ok(())
+    }
+
+    fn text_input_event(&mut self, _ctx: &mut Context, character: char) -> GameResult<()> {
+        if self.console.open {
+            self.console.text(character);
+        }
+        Ok(())
+    }
+
+    fn mouse_motion_event(
+        &mut self,
+        _ctx: &mut Context,
+        x: f32,
+        y: f32,
+        _dx: f32,
+        _dy: f32,
+    ) -> GameResult<()> {
+        self.data.input.mouse = self.screen.viewport.to_virtual(x, y);
+        self.data.input.mouse_moved = true;
+        Ok(())
+    }
+
+    fn mouse_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: MouseButton,
+        x: f32,
+        y: f32,
+    ) -> GameResult<()> {
+        if button == MouseButton::Left {
+            self.data.input.mouse = self.screen.viewport.to_virtual(x, y);
+            self.data.input.clicked = true;
+        }
+        Ok(())
     }
 }
 
+// Every file of `resources`, when built with the `embed` feature.
+const EMBEDDED: &[(&str, &[u8])] = include!(concat!(env!("OUT_DIR"), "/resources.rs"));
+
 fn main() {
-    let resource_dir = path::PathBuf::from("./resources");
-    let mut c = ggez::conf::Conf::new();
-    c.window_mode.width = WIDTH;
-    c.window_mode.height = HEIGHT;
-
-    let (mut ctx, event_loop) = ggez::ContextBuilder::new("breakout", "vinz")
-        .default_conf(c)
-        .window_setup(
-            ggez::conf::WindowSetup::default()
-                .title("BreakOut")
-                .vsync(false),
-        )
-        .add_resource_path(resource_dir)
+    let options = Options::parse(GAME_ID, "Break all the bricks with a ball.", &FLAGS);
+    logging::init(GAME_ID, options.log.as_deref());
+    let resources = if cfg!(feature = "embed") {
+        Resources::Embedded(EMBEDDED)
+    } else {
+        let dir = options.resources.clone();
+        Resources::locate(GAME_ID, dir, env!("CARGO_MANIFEST_DIR"))
+    };
+    log::info!("resources: {}", resources);
+    let settings = Settings::load(GAME_ID);
+    let (mut ctx, event_loop) = engine::context_builder(GAME_ID, "BreakOut", &settings, &resources)
+        .window_mode(options.window_mode(&settings))
         .build()
         .unwrap();
     // let rng = rand::thread_rng();
 
-    let global_state = GlobalState::new(&mut ctx).unwrap();
+    let size = (VIRTUAL_WIDTH, VIRTUAL_HEIGHT);
+    let launcher = Launcher::new(&mut ctx, resources, size, true, move |ctx, assets| {
+        GlobalState::new(ctx, settings, &options, &assets)
+    })
+    .unwrap();
+
+    ggez::event::run(ctx, event_loop, launcher)
+}
+
+#[cfg(test)]
+mod tests {
+    use engine::assets::Manifest;
+
+    use super::SOUNDS;
 
-    ggez::event::run(ctx, event_loop, global_state)
+    #[test]
+    fn the_manifest_lists_every_sound() {
+        let manifest = Manifest::parse(include_str!("../resources/assets.toml")).unwrap();
+        for (_, name) in SOUNDS.iter() {
+            assert!(manifest.sounds.contains_key(*name), "{} is missing", name);
+        }
+        assert!(manifest.fonts.contains_key("breakout"));
+        assert!(manifest.images.contains_key("background"));
+    }
 }
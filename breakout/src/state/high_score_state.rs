@@ -1,86 +1,164 @@
-use ggez;
-use ggez::audio::SoundSource;
-use ggez::event::KeyCode;
+use ggez::input::keyboard::KeyCode;
 // use ggez::input::keyboard;
 use ggez::{Context, GameResult};
 
+use ggez::graphics::Rect;
+
 use cgmath::Point2;
 
-use super::super::Fonts;
-use super::super::GlobalState;
-use super::super::Keys;
+use engine::assets::Fonts;
+use engine::render::Renderer;
+use engine::state::State;
+use engine::text::{self, Align, Column};
+
+use super::super::GameData;
 use super::super::SoundKind;
-use super::super::Sounds;
 use super::HighScoreState;
-use super::State;
 use super::StateKind;
 
-use super::super::HEIGHT;
-use super::super::WIDTH;
+use super::super::VIRTUAL_HEIGHT;
+use super::super::VIRTUAL_WIDTH;
 
 const TEXT: &str = "High Scores";
-const TEXT_LEN: f32 = TEXT.len() as f32;
-
 const EXIT: &str = "Press Escape to return to the main menu";
-const EXIT_LEN: f32 = EXIT.len() as f32;
+
+// rank, name, score
+const COLUMNS: [Column; 3] = [
+    Column {
+        x: 0.0,
+        width: 30.0,
+        align: Align::Right,
+    },
+    Column {
+        x: 45.0,
+        width: 120.0,
+        align: Align::Left,
+    },
+    Column {
+        x: 165.0,
+        width: 70.0,
+        align: Align::Right,
+    },
+];
+const TABLE_WIDTH: f32 = 235.0;
 
 impl HighScoreState {
     pub fn new(fonts: &Fonts) -> HighScoreState {
         HighScoreState {
-            text: ggez::graphics::Text::new((TEXT, fonts.font, fonts.large)),
-            exit: ggez::graphics::Text::new((EXIT, fonts.font, fonts.small)),
+            text: fonts.text(TEXT, fonts.large),
+            exit: fonts.text(EXIT, fonts.small),
         }
     }
 }
 
-impl State for HighScoreState {
+impl State<StateKind, GameData> for HighScoreState {
     fn enter(&mut self, _params: StateKind) {}
 
     fn exit(&self) {}
 
-    fn update(
-        &mut self,
-        sounds: &mut Sounds,
-        keys: &Keys,
-        ctx: &mut Context,
-    ) -> GameResult<Option<StateKind>> {
-        if keys.contains(&KeyCode::Escape) {
-            let key = &SoundKind::WallHit.to_string();
-            sounds.get_mut(key).unwrap().play(ctx)?;
+    fn update(&mut self, data: &mut GameData, ctx: &mut Context) -> GameResult<Option<StateKind>> {
+        if data.input.pressed(KeyCode::Escape) {
+            data.audio.play(ctx, SoundKind::WallHit);
             Ok(Some(StateKind::Start))
         } else {
             Ok(None)
         }
     }
 
-    fn render(&self, global_state: &GlobalState, ctx: &mut Context) -> GameResult<()> {
-        let shift = 15.0 * TEXT_LEN;
-        let pos = Point2::new((WIDTH / 2.0) - shift, HEIGHT / 6.0);
-        ggez::graphics::draw(ctx, &self.text, (pos,))?;
+    fn render(&self, data: &GameData, renderer: &mut dyn Renderer) -> GameResult<()> {
+        let white = ggez::graphics::Color::new(1.0, 1.0, 1.0, 1.0);
+        let area = Rect::new(0.0, VIRTUAL_HEIGHT / 6.0, VIRTUAL_WIDTH, 0.0);
+        text::draw_aligned(renderer, &self.text, area, Align::Center, white);
 
+        let fonts = &data.fonts;
         for i in 0..10 {
-            let hscore = &global_state.high_scores.get(i);
+            let hscore = &data.high_scores.get(i);
             let (name, score) = match hscore {
                 None => ("---".to_string(), "---".to_string()),
                 Some(hscore) => (hscore.name.to_string(), hscore.score.to_string()),
             };
-            let len = name.len();
-            let fonts = &global_state.fonts;
-            let ndx = ggez::graphics::Text::new((i.to_string(), fonts.font, fonts.medium));
-            let mut pos = Point2::new(WIDTH / 4.0, 178.0 + (i as f32) * 38.0);
-            ggez::graphics::draw(ctx, &ndx, (pos,))?;
-
-            let name = ggez::graphics::Text::new((name, fonts.font, fonts.medium));
-            pos.x = pos.x + 20.0;
-            ggez::graphics::draw(ctx, &name, (pos,))?;
-
-            let score = ggez::graphics::Text::new((score, fonts.font, fonts.medium));
-            pos.x = pos.x + (len as f32) * 8.0 + 20.0;
-            ggez::graphics::draw(ctx, &score, (pos,))?;
+            let rank = format!("{}.", i + 1);
+            let cells = [
+                fonts.text(rank, fonts.medium),
+                fonts.text(name, fonts.medium),
+                fonts.text(score, fonts.medium),
+            ];
+            let pos = Point2::new(
+                ((VIRTUAL_WIDTH - TABLE_WIDTH) / 2.0).round(),
+                60.0 + (i as f32) * 13.0,
+            );
+            text::draw_row(renderer, &cells, &COLUMNS, pos, white);
         }
 
-        let shift = 4.0 * EXIT_LEN;
-        let pos = Point2::new((WIDTH / 2.0) - shift, HEIGHT - 50.0);
-        ggez::graphics::draw(ctx, &self.exit, (pos,))
+        let area = Rect::new(0.0, VIRTUAL_HEIGHT - 17.0, VIRTUAL_WIDTH, 0.0);
+        text::draw_aligned(renderer, &self.exit, area, Align::Center, white);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use engine::record::{Command, Recorder};
+    use engine::state::State;
+
+    use super::super::super::{GameData, HighScore};
+    use super::HighScoreState;
+
+    fn render(scores: &[(&str, u32)]) -> Recorder {
+        let high_scores = scores
+            .iter()
+            .map(|&(name, score)| HighScore {
+                name: name.to_string(),
+                score,
+            })
+            .collect();
+        let data = GameData::headless(high_scores);
+        let state = HighScoreState::new(&data.fonts);
+        let mut recorder = Recorder::new();
+        state.render(&data, &mut recorder).unwrap();
+        recorder
+    }
+
+    #[test]
+    fn lists_ten_ranked_rows() {
+        let recorder = render(&[("ALICE", 5000), ("BOB", 3200), ("CAROL", 10)]);
+        let texts = recorder.texts();
+        // The title, three cells per row, then how to leave.
+        assert_eq!(texts.len(), 1 + 10 * 3 + 1);
+        let rows: Vec<&[&str]> = texts[1..31].chunks(3).collect();
+        assert_eq!(rows[0], ["1.", "ALICE", "5000"]);
+        assert_eq!(rows[1], ["2.", "BOB", "3200"]);
+        assert_eq!(rows[2], ["3.", "CAROL", "10"]);
+        for (i, row) in rows.iter().enumerate().skip(3) {
+            let rank = format!("{}.", i + 1);
+            assert_eq!(*row, [rank.as_str(), "---", "---"]);
+        }
+    }
+
+    #[test]
+    fn right_aligns_scores_on_evenly_spaced_rows() {
+        let recorder = render(&[("ALICE", 5000), ("BOB", 10)]);
+        let scores: Vec<(f32, f32)> = recorder.commands[1..31]
+            .chunks(3)
+            .map(|row| match &row[2] {
+                Command::Text {
+                    content, size, pos, ..
+                } => (pos.x + content.len() as f32 * size / 2.0, pos.y),
+                command => panic!("expected a score, got {}", command),
+            })
+            .collect();
+        for (i, &(right, y)) in scores.iter().enumerate() {
+            assert_eq!(right, scores[0].0);
+            assert_eq!(y, 60.0 + i as f32 * 13.0);
+        }
+    }
+
+    #[test]
+    fn matches_snapshot() {
+        let recorder = render(&[("ALICE", 5000), ("BOB", 3200), ("CAROL", 10)]);
+        recorder.assert_snapshot(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/src/state/snapshots/high_score_state.txt"
+        ));
     }
 }
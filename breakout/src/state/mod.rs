@@ -1,13 +1,11 @@
-use super::GlobalState;
-use super::Keys;
-use super::Sounds;
-use ggez::{Context, GameResult};
-use std::collections::HashMap;
+use super::demo::Demo;
+use super::GameData;
 
 pub enum StateKind {
     // EnterHighScores,
     // GameOver,
     HighScores,
+    Options,
     PaddleSelect,
     // Play,
     // Serve,
@@ -15,33 +13,42 @@ pub enum StateKind {
     // Victory,
 }
 
-impl StateKind {
-    fn to_string(&self) -> String {
+impl engine::state::StateKind for StateKind {
+    fn key(&self) -> String {
         match self {
             StateKind::HighScores => "highscores".to_string(),
+            StateKind::Options => "options".to_string(),
             StateKind::PaddleSelect => "paddleselect".to_string(),
             StateKind::Start => "start".to_string(),
         }
     }
 }
 
-pub trait State {
-    fn enter(&mut self, params: StateKind);
-    fn exit(&self);
-    fn update(
-        &mut self,
-        sounds: &mut Sounds,
-        keys: &Keys,
-        ctx: &mut Context,
-    ) -> GameResult<Option<StateKind>>;
-    fn render(&self, global_state: &GlobalState, ctx: &mut Context) -> GameResult<()>;
+impl StateKind {
+    /// The kind of the state added under `key`, for the console.
+    pub fn from_key(key: &str) -> Option<StateKind> {
+        match key {
+            "highscores" => Some(StateKind::HighScores),
+            "options" => Some(StateKind::Options),
+            "paddleselect" => Some(StateKind::PaddleSelect),
+            "start" => Some(StateKind::Start),
+            _ => None,
+        }
+    }
 }
 
+pub type StateMachine = engine::state::StateMachine<StateKind, GameData>;
+
 pub struct StartState {
     highlighted: u32,
     title: ggez::graphics::Text,
     start: ggez::graphics::Text,
     high_score: ggez::graphics::Text,
+    options: ggez::graphics::Text,
+    press_enter: ggez::graphics::Text,
+    // Seconds without input, until the demo starts.
+    idle: f32,
+    demo: Option<Demo>,
 }
 
 pub struct HighScoreState {
@@ -49,60 +56,14 @@ pub struct HighScoreState {
     exit: ggez::graphics::Text,
 }
 
-pub struct StateMachine {
-    pub states: HashMap<String, Box<dyn State>>,
-    current: Option<String>,
-}
-
-impl StateMachine {
-    pub fn new() -> StateMachine {
-        StateMachine {
-            states: HashMap::new(),
-            current: None,
-        }
-    }
-
-    pub fn change(&mut self, params: StateKind) {
-        match &self.current {
-            Some(key) => {
-                let current_state = &self.states[key];
-                current_state.exit()
-            }
-            None => (),
-        }
-        let key = params.to_string();
-        let current = self.states.get_mut(&key).unwrap();
-        current.enter(params);
-        self.current = Some(key)
-    }
-
-    pub fn render(&self, global_state: &GlobalState, ctx: &mut Context) -> GameResult<()> {
-        match &self.current {
-            Some(key) => {
-                let current_state = &self.states[key];
-                current_state.render(global_state, ctx)
-            }
-            None => Ok(()),
-        }
-    }
-    pub fn update(&mut self, sounds: &mut Sounds, keys: &Keys, ctx: &mut Context) -> GameResult {
-        match &mut self.current {
-            Some(key) => {
-                let current_state = self.states.get_mut(key).unwrap();
-                let next_state = current_state.update(sounds, keys, ctx);
-                match next_state {
-                    Ok(Some(new_state)) => {
-                        self.change(new_state);
-                        Ok(())
-                    }
-                    Ok(None) => Ok(()),
-                    Err(_) => panic!(""),
-                }
-            }
-            None => Ok(()),
-        }
-    }
+pub struct OptionsState {
+    highlighted: usize,
+    // The first of the items on screen, as they don't all fit.
+    first: usize,
+    title: ggez::graphics::Text,
+    restart: ggez::graphics::Text,
 }
 
 mod high_score_state;
+mod options_state;
 mod start_state;
@@ -0,0 +1,165 @@
+use ggez::input::keyboard::KeyCode;
+use ggez::{Context, GameResult};
+
+use ggez::graphics::Rect;
+
+use engine::assets::Fonts;
+use engine::render::Renderer;
+use engine::settings::OPTION_ITEMS;
+use engine::state::State;
+use engine::text::{self, Align};
+
+use super::super::GameData;
+use super::super::SoundKind;
+use super::OptionsState;
+use super::StateKind;
+
+use super::super::VIRTUAL_HEIGHT;
+use super::super::VIRTUAL_WIDTH;
+
+const TITLE: &str = "OPTIONS";
+const HELP: &str = "Use Up/Down to select, Left/Right to change, Escape to save and return";
+const RESTART: &str = "(applies on next launch)";
+// Items on screen at once, scrolling to the one highlighted.
+const VISIBLE: usize = 7;
+const TOP: f32 = 75.0;
+const SPACING: f32 = 17.0;
+
+impl OptionsState {
+    pub fn new(fonts: &Fonts) -> OptionsState {
+        OptionsState {
+            highlighted: 0,
+            first: 0,
+            title: fonts.text(TITLE, fonts.large),
+            restart: fonts.text(RESTART, fonts.small),
+        }
+    }
+
+    // Scrolls just enough for the highlighted item to be on screen.
+    fn scroll(&mut self) {
+        if self.highlighted < self.first {
+            self.first = self.highlighted;
+        } else if self.highlighted >= self.first + VISIBLE {
+            self.first = self.highlighted + 1 - VISIBLE;
+        }
+    }
+}
+
+impl State<StateKind, GameData> for OptionsState {
+    fn enter(&mut self, _params: StateKind) {
+        self.highlighted = 0;
+        self.first = 0
+    }
+
+    fn exit(&self) {}
+
+    fn update(&mut self, data: &mut GameData, ctx: &mut Context) -> GameResult<Option<StateKind>> {
+        let len = OPTION_ITEMS.len();
+        if data.input.pressed(KeyCode::Up) {
+            self.highlighted = (self.highlighted + len - 1) % len;
+            data.audio.play(ctx, SoundKind::PaddleHit);
+        }
+        if data.input.pressed(KeyCode::Down) {
+            self.highlighted = (self.highlighted + 1) % len;
+            data.audio.play(ctx, SoundKind::PaddleHit);
+        }
+        self.scroll();
+
+        let step = if data.input.pressed(KeyCode::Left) {
+            -1
+        } else if data.input.pressed(KeyCode::Right) {
+            1
+        } else {
+            0
+        };
+        if step != 0 {
+            OPTION_ITEMS[self.highlighted].adjust(&mut data.settings, step);
+            data.audio.apply_settings(&data.settings);
+            data.juice.apply_settings(&data.settings);
+            data.audio.play(ctx, SoundKind::Select);
+        }
+
+        if data.input.pressed(KeyCode::Escape) {
+            data.settings.save(super::super::GAME_ID);
+            data.audio.play(ctx, SoundKind::WallHit);
+            Ok(Some(StateKind::Start))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn render(&self, data: &GameData, renderer: &mut dyn Renderer) -> GameResult<()> {
+        let white = ggez::graphics::Color::new(1.0, 1.0, 1.0, 1.0);
+        let area = Rect::new(0.0, VIRTUAL_HEIGHT / 6.0, VIRTUAL_WIDTH, 0.0);
+        text::draw_aligned(renderer, &self.title, area, Align::Center, white);
+
+        let fonts = &data.fonts;
+        let shown = OPTION_ITEMS
+            .iter()
+            .enumerate()
+            .skip(self.first)
+            .take(VISIBLE);
+        for (row, (i, item)) in shown.enumerate() {
+            let color = if i == self.highlighted {
+                ggez::graphics::Color::new(103.0 / 255.0, 1.0, 1.0, 1.0)
+            } else {
+                white
+            };
+            let label = item.label(&data.settings);
+            let text = fonts.text(label, fonts.medium);
+            let area = Rect::new(0.0, TOP + row as f32 * SPACING, VIRTUAL_WIDTH, 0.0);
+            text::draw_aligned(renderer, &text, area, Align::Center, color);
+        }
+        // Where the list is, as the others are off screen.
+        let position = format!("{}/{}", self.highlighted + 1, OPTION_ITEMS.len());
+        let text = fonts.text(position, fonts.small);
+        let area = Rect::new(0.0, TOP + VISIBLE as f32 * SPACING, VIRTUAL_WIDTH, 0.0);
+        text::draw_aligned(renderer, &text, area, Align::Center, white);
+
+        if OPTION_ITEMS[self.highlighted].needs_restart() {
+            let area = Rect::new(0.0, VIRTUAL_HEIGHT - 40.0, VIRTUAL_WIDTH, 0.0);
+            text::draw_aligned(renderer, &self.restart, area, Align::Center, white);
+        }
+
+        let area = Rect::new(60.0, VIRTUAL_HEIGHT - 28.0, VIRTUAL_WIDTH - 120.0, 0.0);
+        text::draw_wrapped(
+            renderer,
+            HELP,
+            fonts,
+            fonts.small,
+            area,
+            Align::Center,
+            white,
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use engine::record::Recorder;
+    use engine::settings::OPTION_ITEMS;
+    use engine::state::State;
+
+    use super::super::super::GameData;
+    use super::OptionsState;
+
+    #[test]
+    fn scrolls_to_the_items_off_screen() {
+        let data = GameData::headless(vec![]);
+        let mut state = OptionsState::new(&data.fonts);
+        state.highlighted = OPTION_ITEMS.len() - 1;
+        state.scroll();
+        let mut recorder = Recorder::new();
+        state.render(&data, &mut recorder).unwrap();
+        let texts = recorder.texts();
+        assert!(texts.contains(&"SCORE POPUPS  ON"), "{:?}", texts);
+        assert!(texts.contains(&"12/12"), "{:?}", texts);
+        assert!(!texts.iter().any(|text| text.starts_with("MASTER VOLUME")));
+
+        // Back up to the top.
+        state.highlighted = 0;
+        state.scroll();
+        assert_eq!(state.first, 0);
+    }
+}
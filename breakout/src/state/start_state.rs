@@ -1,100 +1,212 @@
-use ggez;
-use ggez::audio::SoundSource;
-use ggez::event::KeyCode;
+use ggez::input::keyboard::KeyCode;
 // use ggez::input::keyboard;
 use ggez::{Context, GameResult};
 
-use cgmath::Point2;
+use ggez::graphics::Rect;
 
-use super::super::Fonts;
-use super::super::GlobalState;
-use super::super::Keys;
+use engine::assets::Fonts;
+use engine::render::Renderer;
+use engine::state::State;
+use engine::text::{self, Align};
+
+use super::super::demo::Demo;
+use super::super::GameData;
 use super::super::SoundKind;
-use super::super::Sounds;
 use super::StartState;
-use super::State;
 use super::StateKind;
 
-use super::super::HEIGHT;
-use super::super::WIDTH;
+use super::super::VIRTUAL_HEIGHT;
+use super::super::VIRTUAL_WIDTH;
 
 const TITLE: &str = "BREAKOUT";
-const TITLE_LEN: f32 = TITLE.len() as f32;
-
 const START: &str = "START";
-const START_LEN: f32 = START.len() as f32;
-
 const HIGH_SCORES: &str = "HIGH SCORES";
-const HIGH_SCORES_LEN: f32 = HIGH_SCORES.len() as f32;
+const OPTIONS: &str = "OPTIONS";
+const PRESS_ENTER: &str = "PRESS ENTER";
+
+const MENU_ITEMS: u32 = 3;
+const MENU_TOP: f32 = (VIRTUAL_HEIGHT / 2.0) + 50.0;
+const MENU_SPACING: f32 = 20.0;
+
+fn menu_y(item: u32) -> f32 {
+    MENU_TOP + ((item - 1) as f32) * MENU_SPACING
+}
 
 impl StartState {
     pub fn new(fonts: &Fonts) -> StartState {
         StartState {
             highlighted: 1,
-            title: ggez::graphics::Text::new((TITLE, fonts.font, fonts.large)),
-            start: ggez::graphics::Text::new((START, fonts.font, fonts.medium)),
-            high_score: ggez::graphics::Text::new((HIGH_SCORES, fonts.font, fonts.medium)),
+            title: fonts.text(TITLE, fonts.large),
+            start: fonts.text(START, fonts.medium),
+            high_score: fonts.text(HIGH_SCORES, fonts.medium),
+            options: fonts.text(OPTIONS, fonts.medium),
+            press_enter: fonts.text(PRESS_ENTER, fonts.medium),
+            idle: 0.0,
+            demo: None,
+        }
+    }
+
+    fn menu_text(&self, item: u32) -> &ggez::graphics::Text {
+        match item {
+            1 => &self.start,
+            2 => &self.high_score,
+            _ => &self.options,
         }
     }
 }
 
-impl State for StartState {
-    fn enter(&mut self, _params: StateKind) {}
+impl State<StateKind, GameData> for StartState {
+    fn enter(&mut self, _params: StateKind) {
+        self.idle = 0.0;
+        self.demo = None;
+    }
 
     fn exit(&self) {}
 
-    fn update(
-        &mut self,
-        sounds: &mut Sounds,
-        keys: &Keys,
-        ctx: &mut Context,
-    ) -> GameResult<Option<StateKind>> {
-        if keys.contains(&KeyCode::Up) || keys.contains(&KeyCode::Down) {
-            self.highlighted = if self.highlighted == 1 { 2 } else { 1 };
-            let key = &SoundKind::PaddleHit.to_string();
-            sounds.get_mut(key).unwrap().play(ctx)?
+    fn update(&mut self, data: &mut GameData, ctx: &mut Context) -> GameResult<Option<StateKind>> {
+        let input = &data.input;
+        if !input.keys.is_empty() || input.mouse_moved || input.clicked {
+            self.idle = 0.0;
+            // Any input goes back to the menu, and only does that.
+            if self.demo.take().is_some() {
+                data.juice.clear();
+                return Ok(None);
+            }
+        }
+        let dt = ctx.time.delta().as_secs_f32();
+        if let Some(demo) = &mut self.demo {
+            demo.update(dt, &mut data.juice);
+            return Ok(None);
+        }
+        self.idle += dt;
+        let delay = data.settings.attract_delay;
+        if delay > 0.0 && self.idle >= delay {
+            log::info!("starting the demo");
+            self.demo = Some(Demo::new(rand::random()));
         }
 
-        let next = if keys.contains(&KeyCode::Return) {
-            let key = &SoundKind::Confirm.to_string();
-            sounds.get_mut(key).unwrap().play(ctx)?;
-            if self.highlighted == 1 {
-                Some(StateKind::PaddleSelect)
+        if data.input.pressed(KeyCode::Up) {
+            self.highlighted = if self.highlighted == 1 {
+                MENU_ITEMS
             } else {
-                Some(StateKind::HighScores)
+                self.highlighted - 1
+            };
+            data.audio.play(ctx, SoundKind::PaddleHit);
+        }
+        if data.input.pressed(KeyCode::Down) {
+            self.highlighted = self.highlighted % MENU_ITEMS + 1;
+            data.audio.play(ctx, SoundKind::PaddleHit);
+        }
+
+        let hovered = (1..=MENU_ITEMS).find(|&item| {
+            let (width, _) = text::measure(ctx, self.menu_text(item));
+            let y = menu_y(item);
+            (data.input.mouse.x - VIRTUAL_WIDTH / 2.0).abs() <= width / 2.0
+                && data.input.mouse.y >= y
+                && data.input.mouse.y <= y + MENU_SPACING
+        });
+        if let Some(item) = hovered {
+            if data.input.mouse_moved && item != self.highlighted {
+                self.highlighted = item;
+                data.audio.play(ctx, SoundKind::PaddleHit);
+            }
+        }
+        let clicked = data.input.clicked && hovered.is_some();
+
+        let next = if data.input.pressed(KeyCode::Return) || clicked {
+            data.audio.play(ctx, SoundKind::Confirm);
+            match self.highlighted {
+                1 => Some(StateKind::PaddleSelect),
+                2 => Some(StateKind::HighScores),
+                _ => Some(StateKind::Options),
             }
         } else {
             None
         };
 
-        if keys.contains(&KeyCode::Escape) {
-            ggez::event::quit(ctx)
+        if data.input.pressed(KeyCode::Escape) {
+            ctx.request_quit()
         }
 
         Ok(next)
     }
 
-    fn render(&self, _global_state: &GlobalState, ctx: &mut Context) -> GameResult<()> {
-        let title_shift = 15.0 * TITLE_LEN;
-        let title_pos = Point2::new((WIDTH / 2.0) - title_shift, HEIGHT / 3.0);
-        ggez::graphics::draw(ctx, &self.title, (title_pos,))?;
+    fn render(&self, data: &GameData, renderer: &mut dyn Renderer) -> GameResult<()> {
+        let white = ggez::graphics::Color::new(1.0, 1.0, 1.0, 1.0);
+        if let Some(demo) = &self.demo {
+            demo.render(renderer, &data.juice);
+            // Blinks, on for most of each second.
+            if demo.time.fract() < 0.7 {
+                let area = Rect::new(0.0, VIRTUAL_HEIGHT / 2.0, VIRTUAL_WIDTH, 0.0);
+                text::draw_aligned(renderer, &self.press_enter, area, Align::Center, white);
+            }
+            return Ok(());
+        }
+        let area = Rect::new(0.0, VIRTUAL_HEIGHT / 3.0, VIRTUAL_WIDTH, 0.0);
+        text::draw_aligned(renderer, &self.title, area, Align::Center, white);
 
-        let color = if self.highlighted == 1 {
-            ggez::graphics::Color::new(103.0 / 255.0, 1.0, 1.0, 1.0)
-        } else {
-            ggez::graphics::Color::new(1.0, 1.0, 1.0, 1.0)
-        };
-        let start_shift = 10.0 * START_LEN;
-        let start_pos = Point2::new((WIDTH / 2.0) - start_shift, (HEIGHT / 2.0) + 208.0);
-        ggez::graphics::draw(ctx, &self.start, (start_pos, color))?;
+        for item in 1..=MENU_ITEMS {
+            let color = if self.highlighted == item {
+                ggez::graphics::Color::new(103.0 / 255.0, 1.0, 1.0, 1.0)
+            } else {
+                white
+            };
+            let area = Rect::new(0.0, menu_y(item), VIRTUAL_WIDTH, 0.0);
+            text::draw_aligned(renderer, self.menu_text(item), area, Align::Center, color);
+        }
+        Ok(())
+    }
+}
 
-        let color = if self.highlighted == 2 {
-            ggez::graphics::Color::new(103.0 / 255.0, 1.0, 1.0, 1.0)
-        } else {
-            ggez::graphics::Color::new(1.0, 1.0, 1.0, 1.0)
-        };
-        let shift = 9.0 * HIGH_SCORES_LEN;
-        let pos = Point2::new((WIDTH / 2.0) - shift, (HEIGHT / 2.0) + 267.0);
-        ggez::graphics::draw(ctx, &self.high_score, (pos, color))
+#[cfg(test)]
+mod tests {
+    use ggez::graphics::Color;
+
+    use engine::record::{Command, Recorder};
+    use engine::state::State;
+
+    use super::super::super::demo::Demo;
+    use super::super::super::GameData;
+    use super::StartState;
+
+    #[test]
+    fn highlights_the_selected_item() {
+        let data = GameData::headless(vec![]);
+        let mut state = StartState::new(&data.fonts);
+        state.highlighted = 2;
+        let mut recorder = Recorder::new();
+        state.render(&data, &mut recorder).unwrap();
+
+        let highlight = Color::new(103.0 / 255.0, 1.0, 1.0, 1.0);
+        let highlighted: Vec<&str> = recorder
+            .commands
+            .iter()
+            .filter_map(|command| match command {
+                Command::Text { content, color, .. } if *color == highlight => {
+                    Some(content.as_str())
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            recorder.texts(),
+            ["BREAKOUT", "START", "HIGH SCORES", "OPTIONS"]
+        );
+        assert_eq!(highlighted, ["HIGH SCORES"]);
+    }
+
+    #[test]
+    fn shows_the_demo_instead_of_the_menu() {
+        let data = GameData::headless(vec![]);
+        let mut state = StartState::new(&data.fonts);
+        state.demo = Some(Demo::new(1));
+        let mut recorder = Recorder::new();
+        state.render(&data, &mut recorder).unwrap();
+
+        assert_eq!(recorder.texts(), ["PRESS ENTER"]);
+        assert!(recorder
+            .commands
+            .iter()
+            .any(|command| matches!(command, Command::Rect { .. })));
     }
 }
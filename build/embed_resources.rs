@@ -0,0 +1,47 @@
+//! Build script of the games. With the `embed` feature, it lists every file
+//! of the game's `resources` directory in `$OUT_DIR/resources.rs`, as pairs
+//! of its path and `include_bytes!` of it, so that the executable carries
+//! its assets and can be shared on its own. Without it the list is empty.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// The files under `dir`, with their path from `root` as the game asks for
+// them, e.g. "/sounds/score.wav".
+fn files(dir: &Path, root: &Path, found: &mut Vec<(String, PathBuf)>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files(&path, root, found)?;
+            continue;
+        }
+        let relative = path.strip_prefix(root).unwrap_or(&path);
+        let parts: Vec<_> = relative
+            .components()
+            .map(|part| part.as_os_str().to_string_lossy())
+            .collect();
+        found.push((format!("/{}", parts.join("/")), path.clone()));
+    }
+    Ok(())
+}
+
+fn main() -> io::Result<()> {
+    let crate_dir = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").unwrap());
+    let resources = crate_dir.join("resources");
+    println!("cargo:rerun-if-changed={}", resources.display());
+
+    let mut found = Vec::new();
+    if env::var_os("CARGO_FEATURE_EMBED").is_some() {
+        files(&resources, &resources, &mut found)?;
+        found.sort();
+    }
+    let mut code = String::from("&[\n");
+    for (name, path) in &found {
+        code.push_str(&format!("    ({:?}, include_bytes!({:?})),\n", name, path));
+    }
+    code.push_str("]\n");
+    let out = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    fs::write(out.join("resources.rs"), code)
+}
@@ -0,0 +1,126 @@
+//! What drawing 1,000 bricks and a score costs per frame, in draw calls and
+//! allocations, with the shape batch and the text cache.
+//!
+//! `cargo bench -p engine` prints the counts before the timings.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use criterion::{black_box, criterion_group, Criterion};
+use ggez::graphics::{Color, Rect};
+
+use engine::assets::Fonts;
+use engine::render::ShapeBatch;
+use engine::text::TextCache;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const BRICKS: usize = 1000;
+const FRAMES: u32 = 1000;
+
+fn allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    f();
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+// A wall of bricks, as one frame of breakout would queue them.
+fn queue_bricks(batch: &mut ShapeBatch) {
+    for i in 0..BRICKS {
+        let x = (i % 40) as f32 * 32.0;
+        let y = (i / 40) as f32 * 16.0;
+        batch.rect(Rect::new(x, y, 32.0, 16.0), Color::WHITE);
+    }
+}
+
+// The fonts are only referred to by name, so none has to be loaded.
+fn fonts() -> Fonts {
+    Fonts {
+        font: "bench".to_string(),
+        small: 24.0,
+        medium: 48.0,
+        large: 72.0,
+    }
+}
+
+fn report() {
+    let mut batch = ShapeBatch::new();
+    // The first frame sizes the batch, which keeps its memory afterwards.
+    queue_bricks(&mut batch);
+    batch.clear();
+    let allocated = allocations(|| queue_bricks(&mut batch));
+    println!(
+        "{} bricks: {} draw call(s) per frame instead of {}, {} allocation(s)",
+        BRICKS,
+        batch.draw_calls(),
+        batch.len(),
+        allocated
+    );
+
+    // A score going up every 100 frames.
+    let fonts = fonts();
+    let mut cache = TextCache::new(&fonts);
+    let allocated = allocations(|| {
+        for frame in 0..FRAMES {
+            let score = format_args!("{}", frame / 100);
+            black_box(cache.get("score", score, fonts.large));
+        }
+    });
+    println!(
+        "score over {} frames: {} layout(s) instead of {}, {} allocation(s)",
+        FRAMES,
+        cache.layouts(),
+        FRAMES,
+        allocated
+    );
+}
+
+fn bench_shapes(c: &mut Criterion) {
+    let mut batch = ShapeBatch::new();
+    c.bench_function("queue 1000 bricks", |b| {
+        b.iter(|| {
+            batch.clear();
+            queue_bricks(&mut batch);
+            black_box(batch.draw_calls())
+        })
+    });
+}
+
+fn bench_text(c: &mut Criterion) {
+    let fonts = fonts();
+    let mut cache = TextCache::new(&fonts);
+    c.bench_function("unchanged score", |b| {
+        b.iter(|| {
+            black_box(cache.get("score", format_args!("{}", 42), fonts.large));
+        })
+    });
+}
+
+criterion_group!(benches, bench_shapes, bench_text);
+
+fn main() {
+    report();
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+}
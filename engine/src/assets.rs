@@ -0,0 +1,550 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::Deserialize;
+
+use ggez::audio::SoundData;
+use ggez::graphics::{Image, Text, TextFragment};
+use ggez::{Context, GameError, GameResult};
+
+use super::render;
+
+/// A font, registered under `font`, and the sizes a game uses it at.
+pub struct Fonts {
+    pub font: String,
+    pub small: f32,
+    pub medium: f32,
+    pub large: f32,
+}
+
+impl Fonts {
+    pub fn load(ctx: &mut Context, name: &str, path: &str, sizes: [f32; 3]) -> GameResult<Fonts> {
+        render::load_font(ctx, name, path)?;
+        Ok(Fonts::new(name, sizes))
+    }
+
+    /// A font already registered under `name`.
+    pub fn new(name: &str, sizes: [f32; 3]) -> Fonts {
+        Fonts {
+            font: name.to_string(),
+            small: sizes[0],
+            medium: sizes[1],
+            large: sizes[2],
+        }
+    }
+
+    /// `content` laid out in this font, at `size`.
+    pub fn text(&self, content: impl Into<String>, size: f32) -> Text {
+        Text::new(TextFragment::new(content).font(&self.font).scale(size))
+    }
+}
+
+/// Where the assets of a game come from.
+#[derive(Clone)]
+pub enum Resources {
+    /// A directory, mounted in ggez's filesystem.
+    Dir(PathBuf),
+    /// Files built into the executable, by their path in the resource
+    /// directory.
+    Embedded(&'static [(&'static str, &'static [u8])]),
+}
+
+impl Resources {
+    /// The first resource directory that exists among `arg` (given with
+    /// `--resources`), the `<GAME>_RESOURCES` environment variable, the
+    /// `resources` directory next to the executable and the one of the
+    /// game's crate, `crate_dir` being its `CARGO_MANIFEST_DIR`. So the
+    /// game finds its assets wherever it is run from.
+    pub fn locate(game_id: &str, arg: Option<PathBuf>, crate_dir: &str) -> Resources {
+        let var = format!("{}_RESOURCES", game_id.to_uppercase());
+        let next_to_exe = env::current_exe()
+            .ok()
+            .and_then(|exe| Some(exe.parent()?.join("resources")));
+        let crate_resources = Path::new(crate_dir).join("resources");
+        let candidates = arg
+            .into_iter()
+            .chain(env::var_os(var).map(PathBuf::from))
+            .chain(next_to_exe);
+        for dir in candidates {
+            if dir.is_dir() {
+                return Resources::Dir(dir);
+            }
+            log::warn!("no resource directory at {}", dir.display());
+        }
+        Resources::Dir(crate_resources)
+    }
+
+    pub fn dir(&self) -> Option<&Path> {
+        match self {
+            Resources::Dir(dir) => Some(dir),
+            Resources::Embedded(_) => None,
+        }
+    }
+
+    // Straight from the directory: ggez's filesystem would look in the
+    // other places it mounts first.
+    fn read(&self, path: &str) -> Result<Cow<'static, [u8]>, String> {
+        match self {
+            Resources::Dir(dir) => match fs::read(dir.join(path.trim_start_matches('/'))) {
+                Ok(bytes) => Ok(Cow::Owned(bytes)),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Err("file not found".to_string()),
+                Err(e) => Err(e.to_string()),
+            },
+            Resources::Embedded(files) => match files.iter().find(|(file, _)| *file == path) {
+                Some((_, bytes)) => Ok(Cow::Borrowed(bytes)),
+                None => Err("not built into the executable".to_string()),
+            },
+        }
+    }
+
+    fn read_to_string(&self, path: &str) -> Result<String, String> {
+        let bytes = self.read(path)?;
+        String::from_utf8(bytes.into_owned()).map_err(|e| e.to_string())
+    }
+}
+
+impl fmt::Display for Resources {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Resources::Dir(dir) => write!(f, "{}", dir.display()),
+            Resources::Embedded(_) => f.write_str("the executable"),
+        }
+    }
+}
+
+/// Where the manifest is, in the resource directory.
+pub const MANIFEST: &str = "/assets.toml";
+
+/// The assets of a game, by kind, from the logical names the game uses to
+/// their path in the resource directory.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Manifest {
+    pub music: Option<String>,
+    pub fonts: BTreeMap<String, String>,
+    pub images: BTreeMap<String, String>,
+    pub sounds: BTreeMap<String, String>,
+    pub levels: BTreeMap<String, String>,
+}
+
+impl Manifest {
+    pub fn parse(content: &str) -> Result<Manifest, AssetError> {
+        toml::from_str(content).map_err(|e| AssetError::new("the asset manifest", e.to_string()))
+    }
+
+    pub fn read(resources: &Resources) -> Result<Manifest, AssetError> {
+        let content = resources.read_to_string(MANIFEST).map_err(|e| {
+            let asset = format!("the asset manifest ({} in {})", MANIFEST, resources);
+            AssetError::new(asset, e)
+        })?;
+        Manifest::parse(&content)
+    }
+
+    // Fonts first, so that they are ready to show the others loading.
+    fn entries(&self) -> Vec<Entry> {
+        let mut entries = Vec::new();
+        let mut add = |kind, map: &BTreeMap<String, String>| {
+            for (name, path) in map {
+                entries.push(Entry {
+                    kind,
+                    name: name.clone(),
+                    path: path.clone(),
+                })
+            }
+        };
+        add(Kind::Font, &self.fonts);
+        add(Kind::Image, &self.images);
+        add(Kind::Sound, &self.sounds);
+        add(Kind::Level, &self.levels);
+        if let Some(path) = &self.music {
+            entries.push(Entry {
+                kind: Kind::Music,
+                name: "music".to_string(),
+                path: path.clone(),
+            })
+        }
+        entries
+    }
+}
+
+/// An asset that is missing, malformed, or that the manifest doesn't list.
+#[derive(Debug, PartialEq)]
+pub struct AssetError {
+    pub asset: String,
+    pub reason: String,
+}
+
+impl AssetError {
+    fn new(asset: impl Into<String>, reason: impl Into<String>) -> AssetError {
+        AssetError {
+            asset: asset.into(),
+            reason: reason.into(),
+        }
+    }
+
+    fn missing(kind: Kind, name: &str) -> AssetError {
+        AssetError::new(format!("{} {}", kind, name), "not in the asset manifest")
+    }
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "could not load {}: {}", self.asset, self.reason)
+    }
+}
+
+impl From<AssetError> for GameError {
+    fn from(e: AssetError) -> GameError {
+        GameError::ResourceLoadError(e.to_string())
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Kind {
+    Font,
+    Image,
+    Sound,
+    Music,
+    Level,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self {
+            Kind::Font => "font",
+            Kind::Image => "image",
+            Kind::Sound => "sound",
+            Kind::Music => "music",
+            Kind::Level => "level",
+        };
+        f.write_str(kind)
+    }
+}
+
+struct Entry {
+    kind: Kind,
+    name: String,
+    path: String,
+}
+
+impl Entry {
+    fn load(
+        &self,
+        ctx: &mut Context,
+        resources: &Resources,
+        assets: &mut Assets,
+    ) -> Result<(), AssetError> {
+        let fail = |reason| {
+            AssetError::new(
+                format!("{} {} ({})", self.kind, self.name, self.path),
+                reason,
+            )
+        };
+        let name = self.name.clone();
+        let bytes = resources.read(&self.path).map_err(fail)?;
+        match self.kind {
+            Kind::Font => {
+                render::add_font(ctx, &name, bytes).map_err(|e| fail(e.to_string()))?;
+                assets.fonts.push(name);
+            }
+            Kind::Image => {
+                let image = render::decode_image(ctx, &bytes).map_err(|e| fail(e.to_string()))?;
+                let _ = assets.images.insert(name, image);
+            }
+            Kind::Sound | Kind::Music => {
+                let data = SoundData::from_bytes(&bytes);
+                if !data.can_play() {
+                    return Err(fail("not a sound ggez can decode".to_string()));
+                }
+                if let Kind::Music = self.kind {
+                    assets.music = Some(data);
+                } else {
+                    let _ = assets.sounds.insert(name, data);
+                }
+            }
+            Kind::Level => {
+                let level =
+                    String::from_utf8(bytes.into_owned()).map_err(|e| fail(e.to_string()))?;
+                let _ = assets.levels.insert(name, level);
+            }
+        }
+        Ok(())
+    }
+
+    // When the file was last modified, if it is on disk at all.
+    fn modified(&self, root: &Path) -> Option<SystemTime> {
+        let path = root.join(self.path.trim_start_matches('/'));
+        fs::metadata(path).and_then(|meta| meta.modified()).ok()
+    }
+}
+
+/// Everything the manifest lists, once loaded. Fonts are registered with
+/// ggez under their logical name.
+#[derive(Default)]
+pub struct Assets {
+    fonts: Vec<String>,
+    images: HashMap<String, Image>,
+    sounds: HashMap<String, SoundData>,
+    music: Option<SoundData>,
+    levels: HashMap<String, String>,
+}
+
+impl Assets {
+    pub fn fonts(&self, name: &str, sizes: [f32; 3]) -> Result<Fonts, AssetError> {
+        if self.fonts.iter().any(|font| font == name) {
+            Ok(Fonts::new(name, sizes))
+        } else {
+            Err(AssetError::missing(Kind::Font, name))
+        }
+    }
+
+    pub fn image(&self, name: &str) -> Result<Image, AssetError> {
+        match self.images.get(name) {
+            Some(image) => Ok(image.clone()),
+            None => Err(AssetError::missing(Kind::Image, name)),
+        }
+    }
+
+    pub fn sound(&self, name: &str) -> Result<SoundData, AssetError> {
+        match self.sounds.get(name) {
+            Some(data) => Ok(data.clone()),
+            None => Err(AssetError::missing(Kind::Sound, name)),
+        }
+    }
+
+    /// The music is optional.
+    pub fn music(&self) -> Option<SoundData> {
+        self.music.clone()
+    }
+
+    pub fn level(&self, name: &str) -> Result<&str, AssetError> {
+        match self.levels.get(name) {
+            Some(level) => Ok(level),
+            None => Err(AssetError::missing(Kind::Level, name)),
+        }
+    }
+}
+
+/// Loads the assets of a manifest a few at a time, so that a loading
+/// screen can be drawn in between.
+pub struct Loader {
+    resources: Resources,
+    entries: Vec<Entry>,
+    loaded: usize,
+    assets: Assets,
+}
+
+impl Loader {
+    pub fn new(manifest: &Manifest, resources: Resources) -> Loader {
+        Loader {
+            resources,
+            entries: manifest.entries(),
+            loaded: 0,
+            assets: Assets::default(),
+        }
+    }
+
+    /// Between 0 and 1.
+    pub fn progress(&self) -> f32 {
+        if self.entries.is_empty() {
+            1.0
+        } else {
+            self.loaded as f32 / self.entries.len() as f32
+        }
+    }
+
+    pub fn done(&self) -> bool {
+        self.loaded == self.entries.len()
+    }
+
+    /// What is loaded next, to show on the loading screen.
+    pub fn next(&self) -> Option<String> {
+        let entry = self.entries.get(self.loaded)?;
+        Some(format!("{} {}", entry.kind, entry.name))
+    }
+
+    /// Loads assets until `budget` is spent, at least one.
+    pub fn step(&mut self, ctx: &mut Context, budget: Duration) -> Result<(), AssetError> {
+        let start = Instant::now();
+        while !self.done() {
+            self.load_next(ctx)?;
+            if start.elapsed() >= budget {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn load_next(&mut self, ctx: &mut Context) -> Result<(), AssetError> {
+        let entry = &self.entries[self.loaded];
+        entry.load(ctx, &self.resources, &mut self.assets)?;
+        log::debug!("loaded {} {} from {}", entry.kind, entry.name, entry.path);
+        self.loaded += 1;
+        Ok(())
+    }
+
+    pub fn finish(self) -> Assets {
+        let count = self.entries.len();
+        log::info!("loaded {} assets from {}", count, self.resources);
+        self.assets
+    }
+}
+
+// How often the watcher looks at the files.
+const POLL: Duration = Duration::from_millis(500);
+
+/// Assets changed on disk, reloaded by a `Watcher`.
+pub struct Changes {
+    pub assets: Assets,
+    /// What was reloaded, e.g. "image background".
+    pub reloaded: Vec<String>,
+    pub errors: Vec<AssetError>,
+}
+
+/// Reloads the assets of a manifest when their file changes under `root`,
+/// so that they can be edited while the game runs. It polls modification
+/// times, which is cheap for the few dozen files a game has.
+pub struct Watcher {
+    resources: Resources,
+    root: PathBuf,
+    entries: Vec<(Entry, Option<SystemTime>)>,
+    polled: Instant,
+}
+
+impl Watcher {
+    pub fn new(manifest: &Manifest, root: PathBuf) -> Watcher {
+        let entries = manifest
+            .entries()
+            .into_iter()
+            .map(|entry| {
+                let modified = entry.modified(&root);
+                (entry, modified)
+            })
+            .collect();
+        Watcher {
+            resources: Resources::Dir(root.clone()),
+            root,
+            entries,
+            polled: Instant::now(),
+        }
+    }
+
+    /// What changed since the last poll, if anything did.
+    pub fn poll(&mut self, ctx: &mut Context) -> Option<Changes> {
+        if self.polled.elapsed() < POLL {
+            return None;
+        }
+        self.polled = Instant::now();
+        let mut changes = Changes {
+            assets: Assets::default(),
+            reloaded: Vec::new(),
+            errors: Vec::new(),
+        };
+        for (entry, modified) in &mut self.entries {
+            let now = entry.modified(&self.root);
+            if now.is_none() || now == *modified {
+                continue;
+            }
+            *modified = now;
+            match entry.load(ctx, &self.resources, &mut changes.assets) {
+                Ok(()) => changes
+                    .reloaded
+                    .push(format!("{} {}", entry.kind, entry.name)),
+                Err(e) => changes.errors.push(e),
+            }
+        }
+        if changes.reloaded.is_empty() && changes.errors.is_empty() {
+            None
+        } else {
+            Some(changes)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{AssetError, Assets, Manifest, Resources};
+
+    #[test]
+    fn parses_a_manifest() {
+        let manifest = Manifest::parse(
+            r#"
+            music = "/sounds/music.wav"
+
+            [fonts]
+            main = "/fonts/font.ttf"
+
+            [sounds]
+            hit = "/sounds/hit.wav"
+            score = "/sounds/score.wav"
+            "#,
+        )
+        .unwrap();
+        let entries: Vec<String> = manifest
+            .entries()
+            .iter()
+            .map(|entry| format!("{} {} {}", entry.kind, entry.name, entry.path))
+            .collect();
+        let expected = [
+            "font main /fonts/font.ttf",
+            "sound hit /sounds/hit.wav",
+            "sound score /sounds/score.wav",
+            "music music /sounds/music.wav",
+        ];
+        assert_eq!(entries, expected);
+    }
+
+    #[test]
+    fn rejects_unknown_sections() {
+        let error = Manifest::parse("[sprites]\nball = \"/ball.png\"\n").unwrap_err();
+        assert_eq!(error.asset, "the asset manifest");
+    }
+
+    #[test]
+    fn names_assets_missing_from_the_manifest() {
+        let assets = Assets::default();
+        let error = assets.sound("hit").unwrap_err();
+        let expected = AssetError::new("sound hit", "not in the asset manifest");
+        assert_eq!(error, expected);
+        assert_eq!(
+            error.to_string(),
+            "could not load sound hit: not in the asset manifest"
+        );
+    }
+
+    #[test]
+    fn prefers_the_resource_dir_given() {
+        let given = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let resources = Resources::locate("test", Some(given.clone()), "/game");
+        assert_eq!(resources.dir(), Some(given.as_path()));
+    }
+
+    #[test]
+    fn falls_back_to_the_crate_resources() {
+        let missing = PathBuf::from("/no/such/resources");
+        let resources = Resources::locate("test", Some(missing), "/game");
+        assert_eq!(resources.to_string(), "/game/resources");
+    }
+
+    #[test]
+    fn reads_embedded_files() {
+        let files: &[(&str, &[u8])] = &[("/assets.toml", b"[fonts]\nmain = \"/font.ttf\"\n")];
+        let manifest = Manifest::read(&Resources::Embedded(files)).unwrap();
+        assert_eq!(manifest.fonts["main"], "/font.ttf");
+
+        let error = Manifest::read(&Resources::Embedded(&[])).unwrap_err();
+        assert_eq!(
+            error.asset,
+            "the asset manifest (/assets.toml in the executable)"
+        );
+        assert_eq!(error.reason, "not built into the executable");
+    }
+}
@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::path::Path;
+use std::time::Duration;
+
+use ggez::audio;
+use ggez::audio::SoundSource;
+use ggez::Context;
+
+use super::settings::Settings;
+
+// Number of copies of each sound effect that can play at the same time.
+const VOICES: usize = 4;
+// Music volume multiplier while the game is paused.
+const DUCK_LEVEL: f32 = 0.3;
+
+#[derive(Clone, Copy)]
+pub enum Channel {
+    Master,
+    Sfx,
+    Music,
+}
+
+pub struct Volumes {
+    pub master: f32,
+    pub sfx: f32,
+    pub music: f32,
+}
+
+impl Default for Volumes {
+    fn default() -> Volumes {
+        Volumes {
+            master: 1.0,
+            sfx: 1.0,
+            music: 0.5,
+        }
+    }
+}
+
+// A small pool of sources sharing the same data, so that overlapping hits
+// don't cut each other off.
+struct Voices {
+    sources: Vec<audio::Source>,
+    next: usize,
+}
+
+impl Voices {
+    fn load(ctx: &Context, path: &str) -> Option<Voices> {
+        match audio::SoundData::new(ctx, path) {
+            Ok(data) => Voices::from_data(ctx, data, path),
+            Err(e) => {
+                log::warn!("could not load sound {}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    fn from_data(ctx: &Context, data: audio::SoundData, path: &str) -> Option<Voices> {
+        let mut sources = Vec::with_capacity(VOICES);
+        for _ in 0..VOICES {
+            match audio::Source::from_data(ctx, data.clone()) {
+                Ok(source) => sources.push(source),
+                Err(e) => {
+                    log::warn!("could not create source for {}: {}", path, e);
+                    break;
+                }
+            }
+        }
+        if sources.is_empty() {
+            None
+        } else {
+            Some(Voices { sources, next: 0 })
+        }
+    }
+
+    // Prefer an idle voice, otherwise steal the oldest one.
+    fn pick(&mut self) -> &mut audio::Source {
+        let len = self.sources.len();
+        let idle = (0..len)
+            .map(|i| (self.next + i) % len)
+            .find(|&i| self.sources[i].stopped());
+        let index = idle.unwrap_or(self.next);
+        self.next = (index + 1) % len;
+        &mut self.sources[index]
+    }
+
+    fn playing(&self) -> usize {
+        self.sources.iter().filter(|s| s.playing()).count()
+    }
+}
+
+enum Fade {
+    None,
+    In { elapsed: f32, duration: f32 },
+    Out { elapsed: f32, duration: f32 },
+}
+
+struct Music {
+    source: audio::Source,
+    fade: Fade,
+    gain: f32,
+}
+
+/// Plays the sound effects of a game, identified by keys of type `K`
+/// (usually a `SoundKind` enum), and its background music.
+pub struct AudioManager<K> {
+    sounds: HashMap<K, Voices>,
+    music: Option<Music>,
+    volumes: Volumes,
+    ducked: bool,
+    muted: bool,
+}
+
+impl<K: Copy + Eq + Hash> AudioManager<K> {
+    pub fn new() -> AudioManager<K> {
+        AudioManager {
+            sounds: HashMap::new(),
+            music: None,
+            volumes: Volumes::default(),
+            ducked: false,
+            muted: false,
+        }
+    }
+
+    /// Loads a sound effect. A missing or undecodable file only prints a
+    /// warning and leaves that sound silent.
+    pub fn load(&mut self, ctx: &Context, kind: K, path: &str) {
+        match Voices::load(ctx, path) {
+            Some(voices) => {
+                let _ = self.sounds.insert(kind, voices);
+            }
+            None => {
+                let _ = self.sounds.remove(&kind);
+            }
+        }
+    }
+
+    /// Adds a sound effect loaded beforehand, see `assets::Loader`.
+    pub fn add(&mut self, ctx: &Context, kind: K, name: &str, data: audio::SoundData) {
+        match Voices::from_data(ctx, data, name) {
+            Some(voices) => {
+                let _ = self.sounds.insert(kind, voices);
+            }
+            None => {
+                let _ = self.sounds.remove(&kind);
+            }
+        }
+    }
+
+    pub fn load_music<P: AsRef<Path>>(&mut self, ctx: &Context, path: P) {
+        let path = path.as_ref();
+        match audio::Source::new(ctx, path) {
+            Ok(source) => self.set_music(source),
+            Err(e) => {
+                log::warn!("could not load music {}: {}", path.display(), e);
+                self.music = None;
+            }
+        }
+    }
+
+    /// Uses music loaded beforehand.
+    pub fn add_music(&mut self, ctx: &Context, data: audio::SoundData) {
+        match audio::Source::from_data(ctx, data) {
+            Ok(source) => self.set_music(source),
+            Err(e) => {
+                log::warn!("could not create source for music: {}", e);
+                self.music = None;
+            }
+        }
+    }
+
+    fn set_music(&mut self, mut source: audio::Source) {
+        source.set_repeat(true);
+        self.music = Some(Music {
+            source,
+            fade: Fade::None,
+            gain: 0.0,
+        });
+    }
+
+    pub fn play(&mut self, ctx: &Context, kind: K) {
+        if self.muted {
+            return;
+        }
+        let volume = self.volumes.master * self.volumes.sfx;
+        if let Some(voices) = self.sounds.get_mut(&kind) {
+            let source = voices.pick();
+            source.set_volume(volume);
+            if let Err(e) = source.play(ctx) {
+                log::warn!("could not play sound: {}", e);
+            }
+        }
+    }
+
+    /// Starts the background music, fading it in over `fade`.
+    pub fn play_music(&mut self, ctx: &Context, fade: Duration) {
+        if self.muted {
+            return;
+        }
+        if let Some(music) = &mut self.music {
+            music.gain = 0.0;
+            music.fade = Fade::In {
+                elapsed: 0.0,
+                duration: fade.as_secs_f32(),
+            };
+            if let Err(e) = music.source.play(ctx) {
+                log::warn!("could not play music: {}", e);
+            }
+        }
+        self.apply_music_volume();
+    }
+
+    /// Fades the background music out over `fade`, then stops it.
+    pub fn stop_music(&mut self, fade: Duration) {
+        if let Some(music) = &mut self.music {
+            if music.source.playing() {
+                music.fade = Fade::Out {
+                    elapsed: 0.0,
+                    duration: fade.as_secs_f32(),
+                };
+            }
+        }
+    }
+
+    /// Lowers the music while the game is paused.
+    pub fn set_ducked(&mut self, ducked: bool) {
+        self.ducked = ducked;
+        self.apply_music_volume();
+    }
+
+    /// Silences the game for good, for `--no-audio`: nothing is played
+    /// anymore, whatever the volumes.
+    pub fn mute(&mut self) {
+        self.muted = true;
+        if let Some(music) = &mut self.music {
+            music.source.pause();
+        }
+    }
+
+    pub fn volume(&self, channel: Channel) -> f32 {
+        match channel {
+            Channel::Master => self.volumes.master,
+            Channel::Sfx => self.volumes.sfx,
+            Channel::Music => self.volumes.music,
+        }
+    }
+
+    pub fn set_volume(&mut self, channel: Channel, value: f32) {
+        let value = value.clamp(0.0, 1.0);
+        match channel {
+            Channel::Master => self.volumes.master = value,
+            Channel::Sfx => self.volumes.sfx = value,
+            Channel::Music => self.volumes.music = value,
+        }
+        self.apply_music_volume();
+    }
+
+    pub fn apply_settings(&mut self, settings: &Settings) {
+        self.set_volume(Channel::Master, settings.master_volume);
+        self.set_volume(Channel::Sfx, settings.sfx_volume);
+        self.set_volume(Channel::Music, settings.music_volume);
+    }
+
+    /// Number of sound effects currently audible.
+    pub fn playing(&self) -> usize {
+        self.sounds.values().map(Voices::playing).sum()
+    }
+
+    /// Advances music fades, call once per frame.
+    pub fn update(&mut self, ctx: &Context) {
+        let dt = ctx.time.delta().as_secs_f32();
+        if let Some(music) = &mut self.music {
+            match &mut music.fade {
+                Fade::None => (),
+                Fade::In { elapsed, duration } => {
+                    *elapsed += dt;
+                    music.gain = fade_progress(*elapsed, *duration);
+                    if *elapsed >= *duration {
+                        music.fade = Fade::None
+                    }
+                }
+                Fade::Out { elapsed, duration } => {
+                    *elapsed += dt;
+                    music.gain = 1.0 - fade_progress(*elapsed, *duration);
+                    if *elapsed >= *duration {
+                        music.fade = Fade::None;
+                        music.source.pause();
+                        if let Err(e) = music.source.stop(ctx) {
+                            log::warn!("could not stop music: {}", e);
+                        }
+                    }
+                }
+            }
+        }
+        self.apply_music_volume();
+    }
+
+    fn apply_music_volume(&mut self) {
+        let duck = if self.ducked { DUCK_LEVEL } else { 1.0 };
+        let volume = self.volumes.master * self.volumes.music * duck;
+        if let Some(music) = &mut self.music {
+            music.source.set_volume(volume * music.gain);
+        }
+    }
+}
+
+impl<K: Copy + Eq + Hash> Default for AudioManager<K> {
+    fn default() -> AudioManager<K> {
+        AudioManager::new()
+    }
+}
+
+fn fade_progress(elapsed: f32, duration: f32) -> f32 {
+    if duration <= 0.0 {
+        1.0
+    } else {
+        (elapsed / duration).min(1.0)
+    }
+}
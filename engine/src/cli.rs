@@ -0,0 +1,292 @@
+//! Command-line options shared by the games. Each game picks the ones it
+//! supports, so that `--help` only lists what it understands.
+
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use clap::builder::PossibleValuesParser;
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
+
+use ggez::conf::{FullscreenType, WindowMode};
+
+use super::settings::Settings;
+
+/// An option a game can support.
+#[derive(Clone, Copy)]
+pub enum Flag {
+    /// `--windowed` and `--fullscreen`.
+    Window,
+    /// `--size WxH`.
+    Size,
+    /// `--seed N`, to replay the same random events.
+    Seed,
+    /// `--mode`, one of the values given.
+    Mode(&'static [&'static str]),
+    /// `--no-audio`.
+    NoAudio,
+    /// `--state`, one of the states given, to start in.
+    State(&'static [&'static str]),
+    /// `--resources DIR`.
+    Resources,
+    /// `--log SPEC`, see `logging::init`.
+    Log,
+    /// `--telemetry`, to write the events of the session.
+    Telemetry,
+    /// `--balls N`, how many balls can be in play at once.
+    Balls,
+    /// `--power-ups`, to bring power-ups into play.
+    PowerUps,
+    /// `--history`, to keep the statistics of every match.
+    History,
+    /// `--arena NAME`, the arena to play in, from the game's data.
+    Arena,
+}
+
+/// What was given on the command line.
+#[derive(Debug, Default, PartialEq)]
+pub struct Options {
+    pub fullscreen: Option<bool>,
+    pub size: Option<(f32, f32)>,
+    pub seed: Option<u64>,
+    pub mode: Option<String>,
+    pub no_audio: bool,
+    pub state: Option<String>,
+    pub resources: Option<PathBuf>,
+    pub log: Option<String>,
+    pub telemetry: bool,
+    pub balls: Option<u32>,
+    pub power_ups: bool,
+    pub history: bool,
+    pub arena: Option<String>,
+}
+
+impl Options {
+    /// Parses the arguments of the process, printing the help or the error
+    /// and exiting if asked to or if they are wrong.
+    pub fn parse(game_id: &'static str, about: &'static str, flags: &[Flag]) -> Options {
+        let matches = command(game_id, about, flags).get_matches();
+        Options::from_matches(&matches, flags)
+    }
+
+    pub fn try_parse_from<I, T>(
+        game_id: &'static str,
+        about: &'static str,
+        flags: &[Flag],
+        args: I,
+    ) -> Result<Options, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let matches = command(game_id, about, flags).try_get_matches_from(args)?;
+        Ok(Options::from_matches(&matches, flags))
+    }
+
+    fn from_matches(matches: &ArgMatches, flags: &[Flag]) -> Options {
+        let mut options = Options::default();
+        for flag in flags {
+            match flag {
+                Flag::Window => {
+                    if matches.get_flag("fullscreen") {
+                        options.fullscreen = Some(true);
+                    } else if matches.get_flag("windowed") {
+                        options.fullscreen = Some(false);
+                    }
+                }
+                Flag::Size => options.size = matches.get_one("size").copied(),
+                Flag::Seed => options.seed = matches.get_one("seed").copied(),
+                Flag::Mode(_) => options.mode = matches.get_one("mode").cloned(),
+                Flag::NoAudio => options.no_audio = matches.get_flag("no-audio"),
+                Flag::State(_) => options.state = matches.get_one("state").cloned(),
+                Flag::Resources => options.resources = matches.get_one("resources").cloned(),
+                Flag::Log => options.log = matches.get_one("log").cloned(),
+                Flag::Telemetry => options.telemetry = matches.get_flag("telemetry"),
+                Flag::Balls => options.balls = matches.get_one("balls").copied(),
+                Flag::PowerUps => options.power_ups = matches.get_flag("power-ups"),
+                Flag::History => options.history = matches.get_flag("history"),
+                Flag::Arena => options.arena = matches.get_one("arena").cloned(),
+            }
+        }
+        options
+    }
+
+    /// The window from the user's settings, with what the command line
+    /// changes. The settings themselves are left alone, so that these
+    /// changes aren't saved.
+    pub fn window_mode(&self, settings: &Settings) -> WindowMode {
+        let mut mode = settings.window_mode();
+        if let Some((width, height)) = self.size {
+            mode = mode.dimensions(width, height);
+        }
+        match self.fullscreen {
+            Some(true) => mode.fullscreen_type(FullscreenType::Desktop),
+            Some(false) => mode.fullscreen_type(FullscreenType::Windowed),
+            None => mode,
+        }
+    }
+}
+
+fn parse_size(value: &str) -> Result<(f32, f32), String> {
+    let error = || format!("expected WIDTHxHEIGHT, like 1920x1080, got {:?}", value);
+    let (width, height) = value.split_once('x').ok_or_else(error)?;
+    match (width.parse::<u32>(), height.parse::<u32>()) {
+        (Ok(width), Ok(height)) if width > 0 && height > 0 => Ok((width as f32, height as f32)),
+        _ => Err(error()),
+    }
+}
+
+fn command(game_id: &'static str, about: &'static str, flags: &[Flag]) -> Command {
+    let mut command = Command::new(game_id).about(about);
+    for flag in flags {
+        command = match flag {
+            Flag::Window => command
+                .arg(
+                    Arg::new("windowed")
+                        .long("windowed")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("fullscreen")
+                        .help("Play in a window, whatever the settings say"),
+                )
+                .arg(
+                    Arg::new("fullscreen")
+                        .long("fullscreen")
+                        .action(ArgAction::SetTrue)
+                        .help("Play fullscreen, whatever the settings say"),
+                ),
+            Flag::Size => command.arg(
+                Arg::new("size")
+                    .long("size")
+                    .value_name("WxH")
+                    .value_parser(parse_size)
+                    .help("Size of the window, like 1920x1080"),
+            ),
+            Flag::Seed => command.arg(
+                Arg::new("seed")
+                    .long("seed")
+                    .value_name("N")
+                    .value_parser(value_parser!(u64))
+                    .help("Seed of the random events, to reproduce a game"),
+            ),
+            Flag::Mode(modes) => command.arg(
+                Arg::new("mode")
+                    .long("mode")
+                    .value_parser(PossibleValuesParser::new(modes.iter()))
+                    .help("How many players there are"),
+            ),
+            Flag::NoAudio => command.arg(
+                Arg::new("no-audio")
+                    .long("no-audio")
+                    .action(ArgAction::SetTrue)
+                    .help("Play no sound nor music"),
+            ),
+            Flag::State(states) => command.arg(
+                Arg::new("state")
+                    .long("state")
+                    .value_parser(PossibleValuesParser::new(states.iter()))
+                    .help("Start in this state rather than on the title screen"),
+            ),
+            Flag::Resources => command.arg(
+                Arg::new("resources")
+                    .long("resources")
+                    .value_name("DIR")
+                    .value_parser(value_parser!(PathBuf))
+                    .help("Where the assets are, if not found on their own"),
+            ),
+            Flag::Log => command.arg(
+                Arg::new("log")
+                    .long("log")
+                    .value_name("SPEC")
+                    .help("What to log, like debug or warn,engine::assets=debug"),
+            ),
+            Flag::Telemetry => command.arg(
+                Arg::new("telemetry")
+                    .long("telemetry")
+                    .action(ArgAction::SetTrue)
+                    .help("Write the events of the session to a JSON-lines file"),
+            ),
+            Flag::Balls => command.arg(
+                Arg::new("balls")
+                    .long("balls")
+                    .value_name("N")
+                    .value_parser(value_parser!(u32).range(1..=8))
+                    .help("Let up to N balls into play, from 1 to 8"),
+            ),
+            Flag::PowerUps => command.arg(
+                Arg::new("power-ups")
+                    .long("power-ups")
+                    .action(ArgAction::SetTrue)
+                    .help("Bring power-ups into the court"),
+            ),
+            Flag::History => command.arg(
+                Arg::new("history")
+                    .long("history")
+                    .action(ArgAction::SetTrue)
+                    .help("Add the statistics of every match to the history file"),
+            ),
+            Flag::Arena => command.arg(
+                Arg::new("arena")
+                    .long("arena")
+                    .value_name("NAME")
+                    .help("Play in the arena NAME"),
+            ),
+        };
+    }
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Flag, Options};
+
+    const FLAGS: [Flag; 6] = [
+        Flag::Window,
+        Flag::Size,
+        Flag::Seed,
+        Flag::Mode(&["1p", "2p"]),
+        Flag::State(&["start", "play"]),
+        Flag::Balls,
+    ];
+
+    fn parse(args: &[&str]) -> Result<Options, clap::Error> {
+        let args = std::iter::once("game").chain(args.iter().copied());
+        Options::try_parse_from("game", "A game.", &FLAGS, args)
+    }
+
+    #[test]
+    fn parses_the_options_given() {
+        let options = parse(&["--fullscreen", "--size", "1920x1080", "--seed", "7"]).unwrap();
+        let expected = Options {
+            fullscreen: Some(true),
+            size: Some((1920.0, 1080.0)),
+            seed: Some(7),
+            ..Options::default()
+        };
+        assert_eq!(options, expected);
+        let options = parse(&["--balls", "3"]).unwrap();
+        let expected = Options {
+            balls: Some(3),
+            ..Options::default()
+        };
+        assert_eq!(options, expected);
+        assert_eq!(parse(&[]).unwrap(), Options::default());
+    }
+
+    #[test]
+    fn rejects_wrong_values() {
+        assert!(parse(&["--size", "1920"]).is_err());
+        assert!(parse(&["--mode", "net-host"]).is_err());
+        assert!(parse(&["--windowed", "--fullscreen"]).is_err());
+        assert!(parse(&["--balls", "0"]).is_err());
+        assert!(parse(&["--balls", "9"]).is_err());
+        // Not supported by this game.
+        assert!(parse(&["--no-audio"]).is_err());
+    }
+
+    #[test]
+    fn only_lists_the_options_of_the_game() {
+        let help = parse(&["--help"]).unwrap_err().to_string();
+        assert!(help.contains("--state <state>"), "{}", help);
+        assert!(help.contains("[possible values: start, play]"), "{}", help);
+        assert!(!help.contains("--no-audio"), "{}", help);
+    }
+}
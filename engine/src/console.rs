@@ -0,0 +1,302 @@
+//! A drop-down developer console, opened with the backtick key. Each game
+//! declares the commands it accepts as a table of `Command`s run against its
+//! own state.
+
+use std::collections::VecDeque;
+
+use ggez::graphics::{Color, Rect};
+use ggez::input::keyboard::KeyCode;
+
+use cgmath::Point2;
+
+use super::assets::Fonts;
+use super::render::Renderer;
+
+/// Key that opens and closes the console.
+pub const TOGGLE: KeyCode = KeyCode::Grave;
+
+/// Lines of output kept.
+const SCROLLBACK: usize = 100;
+
+const PANEL: Color = Color::new(0.0, 0.0, 0.0, 0.8);
+const PROMPT: Color = Color::new(1.0, 1.0, 0.0, 1.0);
+const ERROR: Color = Color::new(1.0, 0.3, 0.3, 1.0);
+
+/// A command the console accepts, run against the game state `T`. It gets
+/// the words after its name and answers with a line of output.
+pub struct Command<T> {
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub run: fn(&mut T, &[&str]) -> Result<String, String>,
+}
+
+// Understood by every console.
+const BUILTINS: [&str; 2] = ["clear", "help"];
+
+/// Runs `line` against `target`, or lists the commands if it is `help`.
+pub fn execute<T>(commands: &[Command<T>], target: &mut T, line: &str) -> Result<String, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let (name, args) = match words.split_first() {
+        Some((name, args)) => (*name, args),
+        None => return Ok(String::new()),
+    };
+    if name == "help" {
+        let usages: Vec<&str> = commands.iter().map(|command| command.usage).collect();
+        return Ok(usages.join(", "));
+    }
+    match commands.iter().find(|command| command.name == name) {
+        Some(command) => (command.run)(target, args),
+        None => Err(format!("unknown command {:?}, try help", name)),
+    }
+}
+
+/// Parses the argument at `i`, for the commands.
+pub fn arg<F: std::str::FromStr>(args: &[&str], i: usize, usage: &str) -> Result<F, String> {
+    match args.get(i).map(|arg| arg.parse()) {
+        Some(Ok(value)) => Ok(value),
+        _ => Err(format!("usage: {}", usage)),
+    }
+}
+
+struct Line {
+    text: String,
+    error: bool,
+}
+
+#[derive(Default)]
+pub struct Console {
+    pub open: bool,
+    input: String,
+    history: Vec<String>,
+    // Position in the history while going through it with Up and Down.
+    recalled: Option<usize>,
+    output: VecDeque<Line>,
+}
+
+impl Console {
+    pub fn new() -> Console {
+        Console::default()
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// Types `c` at the prompt.
+    pub fn text(&mut self, c: char) {
+        if !c.is_control() && c != '`' {
+            self.input.push(c)
+        }
+    }
+
+    /// Handles a key while the console is open. Gives back the line entered
+    /// when it is a command of the game, to be `execute`d and `print`ed.
+    pub fn key<T>(&mut self, key: KeyCode, commands: &[Command<T>]) -> Option<String> {
+        match key {
+            KeyCode::Return | KeyCode::NumpadEnter => return self.submit(),
+            KeyCode::Back => {
+                let _ = self.input.pop();
+            }
+            KeyCode::Tab => self.complete(commands),
+            KeyCode::Up => self.recall(-1),
+            KeyCode::Down => self.recall(1),
+            KeyCode::Escape => self.open = false,
+            _ => {}
+        }
+        None
+    }
+
+    /// Adds the outcome of a command to the output, and to the log so that
+    /// it is kept after the game exits.
+    pub fn print(&mut self, result: Result<String, String>) {
+        let (text, error) = match result {
+            Ok(text) => (text, false),
+            Err(text) => (text, true),
+        };
+        if text.is_empty() {
+            return;
+        }
+        log::info!("{}", text);
+        self.push(text, error);
+    }
+
+    pub fn output(&self) -> impl Iterator<Item = &str> {
+        self.output.iter().map(|line| line.text.as_str())
+    }
+
+    fn push(&mut self, text: String, error: bool) {
+        if self.output.len() == SCROLLBACK {
+            self.output.pop_front();
+        }
+        self.output.push_back(Line { text, error });
+    }
+
+    fn submit(&mut self) -> Option<String> {
+        let line = self.input.trim().to_string();
+        self.input.clear();
+        self.recalled = None;
+        if line.is_empty() {
+            return None;
+        }
+        if self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+        }
+        self.push(format!("> {}", line), false);
+        if line == "clear" {
+            self.output.clear();
+            return None;
+        }
+        Some(line)
+    }
+
+    fn recall(&mut self, step: isize) {
+        if self.history.is_empty() {
+            return;
+        }
+        let last = self.history.len() - 1;
+        self.recalled = match (self.recalled, step < 0) {
+            (None, true) => Some(last),
+            (None, false) => None,
+            (Some(0), true) => Some(0),
+            (Some(i), true) => Some(i - 1),
+            (Some(i), false) if i == last => None,
+            (Some(i), false) => Some(i + 1),
+        };
+        self.input = match self.recalled {
+            Some(i) => self.history[i].clone(),
+            None => String::new(),
+        };
+    }
+
+    // Completes the command name, or as much of it as all the candidates
+    // share, listing them when there are several.
+    fn complete<T>(&mut self, commands: &[Command<T>]) {
+        if self.input.contains(' ') {
+            return;
+        }
+        let names = commands.iter().map(|command| command.name);
+        let mut candidates: Vec<&str> = names
+            .chain(BUILTINS.iter().copied())
+            .filter(|name| name.starts_with(self.input.as_str()))
+            .collect();
+        candidates.sort_unstable();
+        match candidates.as_slice() {
+            [] => {}
+            [name] => self.input = format!("{} ", name),
+            [first, rest @ ..] => {
+                let mut shared = first.len();
+                for name in rest {
+                    shared = first
+                        .bytes()
+                        .zip(name.bytes())
+                        .take(shared)
+                        .take_while(|(a, b)| a == b)
+                        .count();
+                }
+                self.input = first[..shared].to_string();
+                self.push(candidates.join(" "), false);
+            }
+        }
+    }
+
+    /// Drops down over the top of `area`: the last lines of output that fit,
+    /// then the prompt.
+    pub fn render(&self, renderer: &mut dyn Renderer, fonts: &Fonts, area: Rect) {
+        if !self.open {
+            return;
+        }
+        renderer.rect(area, PANEL);
+        let size = fonts.small;
+        let prompt = fonts.text(format!("> {}_", self.input), size);
+        let mut y = area.bottom() - size;
+        renderer.text(&prompt, Point2::new(area.x, y), PROMPT);
+        for line in self.output.iter().rev() {
+            y -= size;
+            if y < area.y {
+                break;
+            }
+            let color = if line.error { ERROR } else { Color::WHITE };
+            let text = fonts.text(line.text.as_str(), size);
+            renderer.text(&text, Point2::new(area.x, y), color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ggez::input::keyboard::KeyCode;
+
+    use super::{arg, execute, Command, Console};
+
+    struct Game {
+        speed: f32,
+    }
+
+    const COMMANDS: [Command<Game>; 2] = [
+        Command {
+            name: "speed",
+            usage: "speed <pixels/s>",
+            run: |game, args| {
+                game.speed = arg(args, 0, "speed <pixels/s>")?;
+                Ok(format!("speed is {}", game.speed))
+            },
+        },
+        Command {
+            name: "spawn",
+            usage: "spawn <kind>",
+            run: |_, _| Ok(String::new()),
+        },
+    ];
+
+    fn typed(console: &mut Console, line: &str) {
+        for c in line.chars() {
+            console.text(c);
+        }
+    }
+
+    #[test]
+    fn runs_commands_by_name() {
+        let mut game = Game { speed: 0.0 };
+        let result = execute(&COMMANDS, &mut game, "speed 400");
+        assert_eq!(result, Ok("speed is 400".to_string()));
+        assert_eq!(game.speed, 400.0);
+        assert!(execute(&COMMANDS, &mut game, "speed fast").is_err());
+        assert!(execute(&COMMANDS, &mut game, "lives 3").is_err());
+    }
+
+    #[test]
+    fn completes_command_names() {
+        let mut console = Console::new();
+        typed(&mut console, "sp");
+        let _ = console.key(KeyCode::Tab, &COMMANDS);
+        assert_eq!(console.input(), "sp");
+        assert_eq!(console.output().last(), Some("spawn speed"));
+        typed(&mut console, "e");
+        let _ = console.key(KeyCode::Tab, &COMMANDS);
+        assert_eq!(console.input(), "speed ");
+    }
+
+    #[test]
+    fn recalls_previous_lines() {
+        let mut console = Console::new();
+        for line in &["speed 1", "speed 2", "speed 2"] {
+            typed(&mut console, line);
+            assert_eq!(
+                console.key(KeyCode::Return, &COMMANDS).as_deref(),
+                Some(*line)
+            );
+        }
+        let _ = console.key(KeyCode::Up, &COMMANDS);
+        assert_eq!(console.input(), "speed 2");
+        let _ = console.key(KeyCode::Up, &COMMANDS);
+        let _ = console.key(KeyCode::Up, &COMMANDS);
+        assert_eq!(console.input(), "speed 1");
+        let _ = console.key(KeyCode::Down, &COMMANDS);
+        let _ = console.key(KeyCode::Down, &COMMANDS);
+        assert_eq!(console.input(), "");
+    }
+}
@@ -0,0 +1,328 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use ggez::graphics::{Color, Rect};
+
+use cgmath::{Point2, Vector2};
+
+use super::assets::Fonts;
+use super::render::Renderer;
+use super::text::TextCache;
+
+pub fn display_fps(
+    renderer: &mut dyn Renderer,
+    cache: &mut TextCache,
+    fonts: &Fonts,
+    fps: f64,
+    pos: Point2<f32>,
+) {
+    let color = Color::new(0.0, 1.0, 0.0, 1.0);
+    let text = cache.get("fps", format_args!("FPS: {:.2}", fps), fonts.small);
+    renderer.text(text, pos, color)
+}
+
+/// Something the debug overlay can outline.
+pub trait Inspect {
+    fn hitbox(&self) -> Rect;
+
+    /// In pixels per second.
+    fn velocity(&self) -> Option<Vector2<f32>> {
+        None
+    }
+}
+
+/// What the game is doing, as shown by the overlay.
+pub struct Info<'a> {
+    pub state: &'a str,
+    pub entities: usize,
+    pub sounds: usize,
+}
+
+/// Number of frames in the frame-time graph.
+pub const HISTORY: usize = 120;
+
+// Full height of the graph, two frames at 60 FPS.
+const GRAPH_SCALE: f32 = 1000.0 / 30.0;
+const BUDGET: f32 = 1000.0 / 60.0;
+// Velocity arrows show where a body will be in this many seconds.
+const ARROW_SECONDS: f32 = 0.25;
+
+const PANEL: Color = Color::new(0.0, 0.0, 0.0, 0.7);
+const TEXT: Color = Color::new(0.0, 1.0, 0.0, 1.0);
+const UPDATE: Color = Color::new(0.3, 0.6, 1.0, 1.0);
+const DRAW: Color = Color::new(1.0, 0.6, 0.2, 1.0);
+const HITBOX: Color = Color::new(0.0, 1.0, 0.0, 1.0);
+const ARROW: Color = Color::new(1.0, 1.0, 0.0, 1.0);
+
+#[derive(Clone, Copy)]
+struct FrameTime {
+    update: Duration,
+    draw: Duration,
+}
+
+/// Hitboxes, velocities, the current state and a graph of the time spent
+/// updating and drawing the last frames. Toggled by the games with F3.
+pub struct Overlay {
+    pub visible: bool,
+    history: VecDeque<FrameTime>,
+    // Time of the update whose frame is not drawn yet.
+    update: Duration,
+    cache: TextCache,
+}
+
+impl Overlay {
+    pub fn new(fonts: &Fonts) -> Overlay {
+        Overlay {
+            visible: false,
+            history: VecDeque::with_capacity(HISTORY),
+            update: Duration::ZERO,
+            cache: TextCache::new(fonts),
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Frame times are recorded while hidden too, so that the graph is full
+    /// as soon as the overlay shows.
+    pub fn record_update(&mut self, time: Duration) {
+        self.update = time;
+    }
+
+    pub fn record_draw(&mut self, time: Duration) {
+        if self.history.len() == HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(FrameTime {
+            update: self.update,
+            draw: time,
+        });
+        self.update = Duration::ZERO;
+    }
+
+    pub fn frames(&self) -> usize {
+        self.history.len()
+    }
+
+    /// Outlines `bodies` where they are, and lays the inspector and the
+    /// graph out in `panel`.
+    pub fn render(
+        &mut self,
+        renderer: &mut dyn Renderer,
+        fonts: &Fonts,
+        info: &Info,
+        bodies: &[&dyn Inspect],
+        panel: Rect,
+    ) {
+        if !self.visible {
+            return;
+        }
+        for body in bodies {
+            outline(renderer, body.hitbox(), HITBOX);
+            if let Some(velocity) = body.velocity() {
+                arrow(renderer, body.hitbox(), velocity);
+            }
+        }
+
+        renderer.rect(panel, PANEL);
+        let (update, draw) = self.average();
+        let size = fonts.small;
+        let lines = [
+            ("state", format_args!("state: {}", info.state)),
+            ("entities", format_args!("entities: {}", info.entities)),
+            ("sounds", format_args!("sounds: {}", info.sounds)),
+            (
+                "times",
+                format_args!("update {:.2} ms, draw {:.2} ms", update, draw),
+            ),
+        ];
+        let mut y = panel.y;
+        for (slot, content) in lines.iter() {
+            let text = self.cache.get(slot, *content, size);
+            renderer.text(text, Point2::new(panel.x, y), TEXT);
+            y += size;
+        }
+
+        let graph = Rect::new(panel.x, y, panel.w, panel.bottom() - y);
+        self.graph(renderer, graph);
+    }
+
+    // Average update and draw times, in milliseconds.
+    fn average(&self) -> (f32, f32) {
+        if self.history.is_empty() {
+            return (0.0, 0.0);
+        }
+        let frames = self.history.len() as f32;
+        let update: Duration = self.history.iter().map(|frame| frame.update).sum();
+        let draw: Duration = self.history.iter().map(|frame| frame.draw).sum();
+        (
+            update.as_secs_f32() * 1000.0 / frames,
+            draw.as_secs_f32() * 1000.0 / frames,
+        )
+    }
+
+    // One bar per frame, the oldest on the left, update below draw.
+    fn graph(&self, renderer: &mut dyn Renderer, area: Rect) {
+        let width = area.w / HISTORY as f32;
+        let height = |time: Duration| {
+            let ms = time.as_secs_f32() * 1000.0;
+            (ms / GRAPH_SCALE).min(1.0) * area.h
+        };
+        for (i, frame) in self.history.iter().enumerate() {
+            let x = area.x + i as f32 * width;
+            let update = height(frame.update);
+            let draw = height(frame.draw).min(area.h - update);
+            let bottom = area.bottom();
+            renderer.rect(Rect::new(x, bottom - update, width, update), UPDATE);
+            renderer.rect(Rect::new(x, bottom - update - draw, width, draw), DRAW);
+        }
+        let budget = area.bottom() - BUDGET / GRAPH_SCALE * area.h;
+        renderer.line(
+            Point2::new(area.x, budget),
+            Point2::new(area.right(), budget),
+            1.0,
+            TEXT,
+        );
+    }
+}
+
+fn outline(renderer: &mut dyn Renderer, rect: Rect, color: Color) {
+    let corners = [
+        Point2::new(rect.left(), rect.top()),
+        Point2::new(rect.right(), rect.top()),
+        Point2::new(rect.right(), rect.bottom()),
+        Point2::new(rect.left(), rect.bottom()),
+    ];
+    for i in 0..corners.len() {
+        let next = corners[(i + 1) % corners.len()];
+        renderer.line(corners[i], next, 1.0, color);
+    }
+}
+
+// From the center of `hitbox`, with a head of two short strokes.
+fn arrow(renderer: &mut dyn Renderer, hitbox: Rect, velocity: Vector2<f32>) {
+    if velocity.x == 0.0 && velocity.y == 0.0 {
+        return;
+    }
+    let from = hitbox.center();
+    let from = Point2::new(from.x, from.y);
+    let to = from + velocity * ARROW_SECONDS;
+    renderer.line(from, to, 1.0, ARROW);
+
+    let angle = velocity.y.atan2(velocity.x);
+    let head = 6.0;
+    for side in &[-0.5f32, 0.5] {
+        let back = angle + std::f32::consts::PI + side;
+        let end = Point2::new(to.x + back.cos() * head, to.y + back.sin() * head);
+        renderer.line(to, end, 1.0, ARROW);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use cgmath::Vector2;
+    use ggez::graphics::Rect;
+
+    use super::super::assets::Fonts;
+    use super::super::record::{Command, Recorder};
+    use super::{Info, Inspect, Overlay, HISTORY};
+
+    fn fonts() -> Fonts {
+        Fonts {
+            font: "test".to_string(),
+            small: 8.0,
+            medium: 16.0,
+            large: 32.0,
+        }
+    }
+
+    struct Body {
+        velocity: Vector2<f32>,
+    }
+
+    impl Inspect for Body {
+        fn hitbox(&self) -> Rect {
+            Rect::new(10.0, 10.0, 4.0, 4.0)
+        }
+
+        fn velocity(&self) -> Option<Vector2<f32>> {
+            Some(self.velocity)
+        }
+    }
+
+    fn lines(recorder: &Recorder) -> usize {
+        recorder
+            .commands
+            .iter()
+            .filter(|command| matches!(command, Command::Line { .. }))
+            .count()
+    }
+
+    const INFO: Info = Info {
+        state: "play",
+        entities: 1,
+        sounds: 0,
+    };
+
+    #[test]
+    fn keeps_the_last_frames() {
+        let mut overlay = Overlay::new(&fonts());
+        for _ in 0..HISTORY + 10 {
+            overlay.record_update(Duration::from_millis(2));
+            overlay.record_draw(Duration::from_millis(3));
+        }
+        assert_eq!(overlay.frames(), HISTORY);
+        assert_eq!(overlay.average(), (2.0, 3.0));
+    }
+
+    #[test]
+    fn draws_nothing_while_hidden() {
+        let fonts = fonts();
+        let mut overlay = Overlay::new(&fonts);
+        let mut recorder = Recorder::new();
+        let body = Body {
+            velocity: Vector2::new(100.0, 0.0),
+        };
+        let panel = Rect::new(0.0, 100.0, 120.0, 60.0);
+        overlay.render(&mut recorder, &fonts, &INFO, &[&body], panel);
+        assert!(recorder.commands.is_empty());
+    }
+
+    #[test]
+    fn outlines_bodies_and_their_velocity() {
+        let fonts = fonts();
+        let mut overlay = Overlay::new(&fonts);
+        overlay.toggle();
+        let panel = Rect::new(0.0, 100.0, 120.0, 60.0);
+
+        // 4 sides and the budget line.
+        let still = Body {
+            velocity: Vector2::new(0.0, 0.0),
+        };
+        let mut recorder = Recorder::new();
+        overlay.render(&mut recorder, &fonts, &INFO, &[&still], panel);
+        assert_eq!(lines(&recorder), 5);
+
+        // And an arrow of 3 strokes, from the center 0.25 s ahead.
+        let moving = Body {
+            velocity: Vector2::new(100.0, 0.0),
+        };
+        let mut recorder = Recorder::new();
+        overlay.render(&mut recorder, &fonts, &INFO, &[&moving], panel);
+        assert_eq!(lines(&recorder), 8);
+        let expected = "line 12 12 to 37 12 1 #ffff00ff";
+        assert!(recorder.snapshot().lines().any(|line| line == expected));
+        assert_eq!(
+            recorder.texts(),
+            [
+                "state: play",
+                "entities: 1",
+                "sounds: 0",
+                "update 0.00 ms, draw 0.00 ms"
+            ]
+        );
+    }
+}
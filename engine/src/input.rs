@@ -0,0 +1,42 @@
+use std::collections::HashSet;
+
+use ggez::input::keyboard::KeyCode;
+
+use cgmath::Point2;
+
+/// What happened on the keyboard and mouse since the last update.
+pub struct Input {
+    pub keys: HashSet<KeyCode>,
+    // in virtual coordinates
+    pub mouse: Point2<f32>,
+    pub mouse_moved: bool,
+    pub clicked: bool,
+}
+
+impl Input {
+    pub fn new() -> Input {
+        Input {
+            keys: HashSet::new(),
+            mouse: Point2::new(0.0, 0.0),
+            mouse_moved: false,
+            clicked: false,
+        }
+    }
+
+    pub fn pressed(&self, key: KeyCode) -> bool {
+        self.keys.contains(&key)
+    }
+
+    /// Forgets this frame's events, call at the end of each update.
+    pub fn clear(&mut self) {
+        self.keys.clear();
+        self.mouse_moved = false;
+        self.clicked = false;
+    }
+}
+
+impl Default for Input {
+    fn default() -> Input {
+        Input::new()
+    }
+}
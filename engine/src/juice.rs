@@ -0,0 +1,285 @@
+//! Game feel: the screen shaking on impacts, paddles flashing when hit,
+//! balls leaving a fading trail and squashing as they bounce, and popups
+//! over scores. The games tell what happened, and each effect only shows
+//! when it is on in the settings.
+
+use ggez::graphics::{Color, Rect};
+
+use cgmath::{Point2, Vector2};
+
+use super::assets::Fonts;
+use super::render::Renderer;
+use super::settings::Settings;
+
+// The most the screen moves, in pixels, and how fast shakes die out.
+const MAX_SHAKE: f32 = 8.0;
+const SHAKE_DECAY: f32 = 10.0;
+const FLASH_TIME: f32 = 0.15;
+const FLASH: Color = Color::new(1.0, 0.9, 0.4, 1.0);
+const TRAIL_LENGTH: usize = 8;
+const SQUASH_TIME: f32 = 0.12;
+const POPUP_TIME: f32 = 0.8;
+const POPUP_RISE: f32 = 40.0;
+
+struct Popup {
+    text: String,
+    pos: Point2<f32>,
+    color: Color,
+    age: f32,
+}
+
+/// The effects in progress. Flashes, trails and squashes are kept by a key
+/// the game chooses, like the index of a paddle or ball.
+#[derive(Default)]
+pub struct Juice {
+    shake_on: bool,
+    flash_on: bool,
+    trail_on: bool,
+    squash_on: bool,
+    popup_on: bool,
+    time: f32,
+    shake: f32,
+    flashes: Vec<(usize, f32)>,
+    trails: Vec<(usize, Vec<Rect>)>,
+    // Along which axis, and for how long still.
+    squashes: Vec<(usize, Vector2<f32>, f32)>,
+    popups: Vec<Popup>,
+}
+
+impl Juice {
+    /// Every effect off until the settings are applied.
+    pub fn new() -> Juice {
+        Juice::default()
+    }
+
+    pub fn apply_settings(&mut self, settings: &Settings) {
+        self.shake_on = settings.screen_shake;
+        self.flash_on = settings.hit_flash;
+        self.trail_on = settings.ball_trail;
+        self.squash_on = settings.squash;
+        self.popup_on = settings.score_popups;
+        if !self.trail_on {
+            self.trails.clear();
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.time += dt;
+        self.shake *= (-SHAKE_DECAY * dt).exp();
+        for (_, left) in &mut self.flashes {
+            *left -= dt;
+        }
+        self.flashes.retain(|&(_, left)| left > 0.0);
+        for (_, _, left) in &mut self.squashes {
+            *left -= dt;
+        }
+        self.squashes.retain(|&(_, _, left)| left > 0.0);
+        for popup in &mut self.popups {
+            popup.age += dt;
+        }
+        self.popups.retain(|popup| popup.age < POPUP_TIME);
+    }
+
+    /// Everything stops at once, e.g. when a new round starts.
+    pub fn clear(&mut self) {
+        self.shake = 0.0;
+        self.flashes.clear();
+        self.trails.clear();
+        self.squashes.clear();
+    }
+
+    /// Shakes the screen, more for faster impacts, in pixels per second.
+    pub fn shake(&mut self, speed: f32) {
+        if self.shake_on {
+            self.shake = self.shake.max((speed / 60.0).min(MAX_SHAKE));
+        }
+    }
+
+    /// How far the screen is moved by the shaking.
+    pub fn offset(&self) -> Vector2<f32> {
+        if self.shake < 0.1 {
+            return Vector2::new(0.0, 0.0);
+        }
+        let t = self.time;
+        let x = (t * 71.0).sin() + (t * 113.0).sin() * 0.5;
+        let y = (t * 83.0).cos() + (t * 97.0).cos() * 0.5;
+        Vector2::new(x, y) * (self.shake / 1.5)
+    }
+
+    pub fn flash(&mut self, key: usize) {
+        if !self.flash_on {
+            return;
+        }
+        self.flashes.retain(|&(k, _)| k != key);
+        self.flashes.push((key, FLASH_TIME));
+    }
+
+    /// `color`, turned towards the flash color while `key` flashes.
+    pub fn tint(&self, key: usize, color: Color) -> Color {
+        let flash = self.flashes.iter().find(|&&(k, _)| k == key);
+        let amount = flash.map_or(0.0, |&(_, left)| left / FLASH_TIME);
+        let mix = |from: f32, to: f32| from + (to - from) * amount;
+        Color::new(
+            mix(color.r, FLASH.r),
+            mix(color.g, FLASH.g),
+            mix(color.b, FLASH.b),
+            color.a,
+        )
+    }
+
+    /// Where `key` is this frame, for its trail.
+    pub fn trail(&mut self, key: usize, rect: Rect) {
+        if !self.trail_on {
+            return;
+        }
+        let index = match self.trails.iter().position(|(k, _)| *k == key) {
+            Some(index) => index,
+            None => {
+                self.trails.push((key, Vec::new()));
+                self.trails.len() - 1
+            }
+        };
+        let trail = &mut self.trails[index].1;
+        if trail.len() == TRAIL_LENGTH {
+            let _ = trail.remove(0);
+        }
+        trail.push(rect);
+    }
+
+    /// The trails, oldest first, smaller and fainter than `color` the older
+    /// they are.
+    pub fn render_trails(&self, renderer: &mut dyn Renderer, color: Color) {
+        for (_, trail) in &self.trails {
+            for (i, rect) in trail.iter().enumerate() {
+                let age = 1.0 - (i + 1) as f32 / (trail.len() + 1) as f32;
+                let scale = 1.0 - age * 0.6;
+                let (w, h) = (rect.w * scale, rect.h * scale);
+                let faded = Rect::new(rect.center().x - w / 2.0, rect.center().y - h / 2.0, w, h);
+                let alpha = color.a * (1.0 - age) * 0.5;
+                renderer.rect(faded, Color::new(color.r, color.g, color.b, alpha));
+            }
+        }
+    }
+
+    /// `key` bounced off a surface facing `normal`.
+    pub fn squash(&mut self, key: usize, normal: Vector2<f32>) {
+        if !self.squash_on {
+            return;
+        }
+        self.squashes.retain(|&(k, _, _)| k != key);
+        self.squashes.push((key, normal, SQUASH_TIME));
+    }
+
+    /// How much to scale `key` along x and y: flattened against what it hit,
+    /// and stretched along it, back to 1 as the squash ends.
+    pub fn scale(&self, key: usize) -> Vector2<f32> {
+        let squash = self.squashes.iter().find(|&&(k, _, _)| k == key);
+        match squash {
+            Some(&(_, normal, left)) => {
+                let amount = 0.4 * left / SQUASH_TIME;
+                let (along, across) = (1.0 - amount, 1.0 + amount);
+                if normal.x.abs() > normal.y.abs() {
+                    Vector2::new(along, across)
+                } else {
+                    Vector2::new(across, along)
+                }
+            }
+            None => Vector2::new(1.0, 1.0),
+        }
+    }
+
+    /// Text rising from `pos` and fading, like the points just scored.
+    pub fn popup(&mut self, text: impl Into<String>, pos: Point2<f32>, color: Color) {
+        if self.popup_on {
+            self.popups.push(Popup {
+                text: text.into(),
+                pos,
+                color,
+                age: 0.0,
+            });
+        }
+    }
+
+    pub fn render_popups(&self, renderer: &mut dyn Renderer, fonts: &Fonts) {
+        for popup in &self.popups {
+            let progress = popup.age / POPUP_TIME;
+            let pos = Point2::new(popup.pos.x, popup.pos.y - POPUP_RISE * progress);
+            let color = Color {
+                a: popup.color.a * (1.0 - progress),
+                ..popup.color
+            };
+            let text = fonts.text(popup.text.as_str(), fonts.medium);
+            renderer.text(&text, pos, color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ggez::graphics::{Color, Rect};
+
+    use cgmath::Vector2;
+
+    use super::super::record::{Command, Recorder};
+    use super::super::settings::Settings;
+    use super::Juice;
+
+    #[test]
+    fn does_nothing_when_turned_off() {
+        let mut juice = Juice::new();
+        let settings = Settings {
+            screen_shake: false,
+            hit_flash: false,
+            ..Settings::default()
+        };
+        juice.apply_settings(&settings);
+        juice.shake(600.0);
+        juice.flash(0);
+        assert_eq!(juice.offset(), Vector2::new(0.0, 0.0));
+        assert_eq!(juice.tint(0, Color::WHITE), Color::WHITE);
+
+        // The squash is still on.
+        juice.squash(0, Vector2::new(1.0, 0.0));
+        let scale = juice.scale(0);
+        assert!(scale.x < 1.0 && scale.y > 1.0, "{:?}", scale);
+        juice.update(1.0);
+        assert_eq!(juice.scale(0), Vector2::new(1.0, 1.0));
+    }
+
+    #[test]
+    fn shakes_harder_for_faster_impacts_and_settles() {
+        let mut juice = Juice::new();
+        juice.apply_settings(&Settings::default());
+        juice.shake(120.0);
+        let soft = juice.shake;
+        juice.shake(400.0);
+        assert!(juice.shake > soft);
+        juice.update(1.0);
+        assert_eq!(juice.offset(), Vector2::new(0.0, 0.0));
+    }
+
+    #[test]
+    fn trails_fade_behind_the_ball() {
+        let mut juice = Juice::new();
+        juice.apply_settings(&Settings::default());
+        for x in 0..10 {
+            juice.trail(0, Rect::new(x as f32 * 10.0, 0.0, 12.0, 12.0));
+        }
+        let mut recorder = Recorder::new();
+        juice.render_trails(&mut recorder, Color::WHITE);
+        let alphas: Vec<f32> = recorder
+            .commands
+            .iter()
+            .map(|command| match command {
+                Command::Rect { color, .. } => color.a,
+                _ => panic!("not a rect: {:?}", command),
+            })
+            .collect();
+        assert_eq!(alphas.len(), 8);
+        assert!(
+            alphas.windows(2).all(|pair| pair[0] < pair[1]),
+            "{:?}",
+            alphas
+        );
+    }
+}
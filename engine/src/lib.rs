@@ -0,0 +1,43 @@
+//! Shared building blocks for the games of this workspace.
+
+use ggez::conf::Conf;
+use ggez::ContextBuilder;
+
+pub mod assets;
+pub mod audio;
+pub mod cli;
+pub mod console;
+pub mod debug;
+pub mod input;
+pub mod juice;
+pub mod loading;
+pub mod logging;
+pub mod record;
+pub mod render;
+pub mod save;
+pub mod settings;
+pub mod state;
+pub mod telemetry;
+pub mod text;
+pub mod viewport;
+
+use assets::Resources;
+use settings::Settings;
+
+/// A `ContextBuilder` for `game_id`, with the window configured from the
+/// user's settings and the resource directory, if any, mounted.
+pub fn context_builder(
+    game_id: &str,
+    title: &str,
+    settings: &Settings,
+    resources: &Resources,
+) -> ContextBuilder {
+    let conf = Conf::new().window_mode(settings.window_mode());
+    let builder = ContextBuilder::new(game_id, "vinz")
+        .default_conf(conf)
+        .window_setup(settings.window_setup(title));
+    match resources.dir() {
+        Some(dir) => builder.add_resource_path(dir),
+        None => builder,
+    }
+}
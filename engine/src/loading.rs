@@ -0,0 +1,436 @@
+//! Starts a game once its assets are loaded, showing their progress, or
+//! what went wrong instead of panicking. In development builds, assets are
+//! then reloaded when their file changes.
+
+use std::time::{Duration, Instant};
+
+use ggez::event::{EventHandler, MouseButton};
+use ggez::graphics::{Color, Rect};
+use ggez::input::keyboard::{KeyCode, KeyInput};
+use ggez::{Context, GameError, GameResult};
+
+use super::assets::{Assets, Changes, Fonts, Loader, Manifest, Resources, Watcher};
+use super::render::Renderer;
+use super::text::{self, Align};
+use super::viewport::Screen;
+
+// The font ggez always has, since the game's own may be what failed.
+const DEFAULT_FONT: &str = "LiberationMono-Regular";
+
+// Time spent loading per frame, to keep the progress bar moving.
+const BUDGET: Duration = Duration::from_millis(10);
+
+const BAR: Color = Color::new(0.4, 0.8, 1.0, 1.0);
+const BAR_BACKGROUND: Color = Color::new(0.2, 0.2, 0.2, 1.0);
+const ERROR: Color = Color::new(1.0, 0.3, 0.3, 1.0);
+const TOAST: Color = Color::new(0.0, 0.0, 0.0, 0.8);
+const RELOADED: Color = Color::new(0.4, 1.0, 0.4, 1.0);
+const TRANSPARENT: Color = Color::new(0.0, 0.0, 0.0, 0.0);
+
+// How long a reload message stays on screen.
+const TOAST_TIME: Duration = Duration::from_secs(4);
+
+/// A game whose assets can change while it runs.
+pub trait Reload {
+    /// Puts the reloaded `assets` in place of the old ones. Only what
+    /// changed is in there.
+    fn reload(&mut self, ctx: &mut Context, assets: &Assets) -> GameResult<()>;
+}
+
+type Build<G> = Box<dyn FnOnce(&mut Context, Assets) -> GameResult<G>>;
+
+enum Stage<G> {
+    Loading(Box<Loader>, Build<G>),
+    Failed(String),
+    Running(G),
+    // Only while moving from loading to running.
+    Empty,
+}
+
+/// Loads the assets listed in the manifest of `resources`, then builds the game from
+/// them and hands it every event.
+pub struct Launcher<G> {
+    stage: Stage<G>,
+    screen: Screen,
+    fonts: Fonts,
+    watcher: Option<Watcher>,
+    toast: Option<Toast>,
+}
+
+struct Toast {
+    message: String,
+    error: bool,
+    until: Instant,
+}
+
+impl<G: EventHandler + Reload> Launcher<G> {
+    pub fn new<F>(
+        ctx: &mut Context,
+        resources: Resources,
+        (width, height): (f32, f32),
+        pixel_art: bool,
+        build: F,
+    ) -> GameResult<Launcher<G>>
+    where
+        F: FnOnce(&mut Context, Assets) -> GameResult<G> + 'static,
+    {
+        let mut watcher = None;
+        let stage = match Manifest::read(&resources) {
+            Ok(manifest) => {
+                if let (true, Some(dir)) = (cfg!(debug_assertions), resources.dir()) {
+                    watcher = Some(Watcher::new(&manifest, dir.to_path_buf()));
+                }
+                let loader = Loader::new(&manifest, resources);
+                Stage::Loading(Box::new(loader), Box::new(build))
+            }
+            Err(e) => failed(e.to_string()),
+        };
+        let size = height / 20.0;
+        Ok(Launcher {
+            stage,
+            screen: Screen::new(ctx, width, height, pixel_art)?,
+            fonts: Fonts::new(DEFAULT_FONT, [size, size * 1.5, size * 2.0]),
+            watcher,
+            toast: None,
+        })
+    }
+
+    // Reload errors only get a toast, the game keeps the assets it had.
+    fn reload(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let changes = match &mut self.watcher {
+            Some(watcher) => watcher.poll(ctx),
+            None => None,
+        };
+        let Changes {
+            assets,
+            reloaded,
+            errors,
+        } = match changes {
+            Some(changes) => changes,
+            None => return Ok(()),
+        };
+        let mut messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        if let Stage::Running(game) = &mut self.stage {
+            if let Err(e) = game.reload(ctx, &assets) {
+                messages.push(e.to_string());
+            }
+        }
+        let error = !messages.is_empty();
+        if !error {
+            messages.push(format!("reloaded {}", reloaded.join(", ")));
+        }
+        let message = messages.join("; ");
+        if error {
+            log::warn!("{}", message);
+        } else {
+            log::info!("{}", message);
+        }
+        self.toast = Some(Toast {
+            message,
+            error,
+            until: Instant::now() + TOAST_TIME,
+        });
+        Ok(())
+    }
+
+    fn area(&self) -> Rect {
+        let viewport = &self.screen.viewport;
+        Rect::new(0.0, 0.0, viewport.width, viewport.height)
+    }
+}
+
+fn failed<G>(message: String) -> Stage<G> {
+    log::error!("{}", message);
+    Stage::Failed(message)
+}
+
+/// A bar filled up to `progress`, and what is being loaded.
+pub fn render_loading(
+    renderer: &mut dyn Renderer,
+    fonts: &Fonts,
+    area: Rect,
+    progress: f32,
+    next: Option<&str>,
+) {
+    let bar = Rect::new(
+        area.x + area.w / 4.0,
+        area.y + area.h / 2.0,
+        area.w / 2.0,
+        fonts.small,
+    );
+    renderer.rect(bar, BAR_BACKGROUND);
+    let filled = Rect::new(bar.x, bar.y, bar.w * progress.clamp(0.0, 1.0), bar.h);
+    renderer.rect(filled, BAR);
+    let label = match next {
+        Some(next) => format!("Loading {}", next),
+        None => "Loading".to_string(),
+    };
+    let label = fonts.text(label, fonts.small);
+    let line = Rect::new(area.x, bar.bottom() + fonts.small, area.w, 0.0);
+    text::draw_aligned(renderer, &label, line, Align::Center, Color::WHITE);
+}
+
+/// A message along the bottom of `area`, over the game.
+pub fn render_toast(
+    renderer: &mut dyn Renderer,
+    fonts: &Fonts,
+    area: Rect,
+    message: &str,
+    error: bool,
+) {
+    let size = fonts.small;
+    let lines = text::wrap(renderer, message, fonts, size, area.w);
+    let height = lines.len() as f32 * size;
+    let panel = Rect::new(area.x, area.bottom() - height, area.w, height);
+    renderer.rect(panel, TOAST);
+    let color = if error { ERROR } else { RELOADED };
+    for (i, line) in lines.iter().enumerate() {
+        let y = panel.y + i as f32 * size;
+        text::draw_aligned(
+            renderer,
+            line,
+            Rect::new(area.x, y, area.w, 0.0),
+            Align::Center,
+            color,
+        );
+    }
+}
+
+/// What failed, and how to leave.
+pub fn render_error(renderer: &mut dyn Renderer, fonts: &Fonts, area: Rect, message: &str) {
+    let title = fonts.text("The game could not start", fonts.medium);
+    let line = Rect::new(area.x, area.y + area.h / 4.0, area.w, 0.0);
+    text::draw_aligned(renderer, &title, line, Align::Center, ERROR);
+    let margin = area.w / 10.0;
+    let body = Rect::new(
+        area.x + margin,
+        line.y + fonts.medium * 2.0,
+        area.w - 2.0 * margin,
+        0.0,
+    );
+    let size = fonts.small;
+    text::draw_wrapped(
+        renderer,
+        message,
+        fonts,
+        size,
+        body,
+        Align::Center,
+        Color::WHITE,
+    );
+    let exit = fonts.text("Press Escape to quit", size);
+    let line = Rect::new(area.x, area.bottom() - size * 3.0, area.w, 0.0);
+    text::draw_aligned(renderer, &exit, line, Align::Center, Color::WHITE);
+}
+
+impl<G: EventHandler + Reload> EventHandler for Launcher<G> {
+    fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let loader = match &mut self.stage {
+            Stage::Running(_) => {
+                self.reload(ctx)?;
+                match &mut self.stage {
+                    Stage::Running(game) => return game.update(ctx),
+                    _ => return Ok(()),
+                }
+            }
+            Stage::Loading(loader, _) => loader,
+            _ => return Ok(()),
+        };
+        if let Err(e) = loader.step(ctx, BUDGET) {
+            self.stage = failed(e.to_string());
+            return Ok(());
+        }
+        if !loader.done() {
+            return Ok(());
+        }
+        if let Stage::Loading(loader, build) = std::mem::replace(&mut self.stage, Stage::Empty) {
+            self.stage = match build(ctx, loader.finish()) {
+                Ok(game) => Stage::Running(game),
+                Err(e) => failed(e.to_string()),
+            };
+        }
+        Ok(())
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
+        if let Stage::Running(game) = &mut self.stage {
+            game.draw(ctx)?;
+            let toast = match &self.toast {
+                Some(toast) if toast.until > Instant::now() => toast,
+                _ => return Ok(()),
+            };
+            let area = self.area();
+            let mut frame = self.screen.begin(ctx, TRANSPARENT)?;
+            render_toast(&mut frame, &self.fonts, area, &toast.message, toast.error);
+            return self.screen.end_over(frame);
+        }
+        let area = self.area();
+        let mut frame = self.screen.begin(ctx, Color::BLACK)?;
+        match &self.stage {
+            Stage::Loading(loader, _) => {
+                let next = loader.next();
+                let next = next.as_deref();
+                render_loading(&mut frame, &self.fonts, area, loader.progress(), next);
+            }
+            Stage::Failed(message) => render_error(&mut frame, &self.fonts, area, message),
+            _ => {}
+        }
+        self.screen.end(frame)
+    }
+
+    fn mouse_button_down_event(
+        &mut self,
+        ctx: &mut Context,
+        button: MouseButton,
+        x: f32,
+        y: f32,
+    ) -> GameResult<()> {
+        match &mut self.stage {
+            Stage::Running(game) => game.mouse_button_down_event(ctx, button, x, y),
+            _ => Ok(()),
+        }
+    }
+
+    fn mouse_button_up_event(
+        &mut self,
+        ctx: &mut Context,
+        button: MouseButton,
+        x: f32,
+        y: f32,
+    ) -> GameResult<()> {
+        match &mut self.stage {
+            Stage::Running(game) => game.mouse_button_up_event(ctx, button, x, y),
+            _ => Ok(()),
+        }
+    }
+
+    fn mouse_motion_event(
+        &mut self,
+        ctx: &mut Context,
+        x: f32,
+        y: f32,
+        dx: f32,
+        dy: f32,
+    ) -> GameResult<()> {
+        match &mut self.stage {
+            Stage::Running(game) => game.mouse_motion_event(ctx, x, y, dx, dy),
+            _ => Ok(()),
+        }
+    }
+
+    fn mouse_wheel_event(&mut self, ctx: &mut Context, x: f32, y: f32) -> GameResult<()> {
+        match &mut self.stage {
+            Stage::Running(game) => game.mouse_wheel_event(ctx, x, y),
+            _ => Ok(()),
+        }
+    }
+
+    fn key_down_event(
+        &mut self,
+        ctx: &mut Context,
+        input: KeyInput,
+        repeat: bool,
+    ) -> GameResult<()> {
+        match &mut self.stage {
+            Stage::Running(game) => game.key_down_event(ctx, input, repeat),
+            _ => {
+                if input.keycode == Some(KeyCode::Escape) {
+                    ctx.request_quit();
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn key_up_event(&mut self, ctx: &mut Context, input: KeyInput) -> GameResult<()> {
+        match &mut self.stage {
+            Stage::Running(game) => game.key_up_event(ctx, input),
+            _ => Ok(()),
+        }
+    }
+
+    fn text_input_event(&mut self, ctx: &mut Context, character: char) -> GameResult<()> {
+        match &mut self.stage {
+            Stage::Running(game) => game.text_input_event(ctx, character),
+            _ => Ok(()),
+        }
+    }
+
+    fn focus_event(&mut self, ctx: &mut Context, gained: bool) -> GameResult<()> {
+        match &mut self.stage {
+            Stage::Running(game) => game.focus_event(ctx, gained),
+            _ => Ok(()),
+        }
+    }
+
+    fn quit_event(&mut self, ctx: &mut Context) -> Result<bool, GameError> {
+        match &mut self.stage {
+            Stage::Running(game) => game.quit_event(ctx),
+            _ => Ok(false),
+        }
+    }
+
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) -> GameResult<()> {
+        self.screen.resize(width, height);
+        match &mut self.stage {
+            Stage::Running(game) => game.resize_event(ctx, width, height),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ggez::graphics::Rect;
+
+    use super::super::assets::Fonts;
+    use super::super::record::{Command, Recorder};
+    use super::{render_error, render_loading, render_toast, BAR, TOAST};
+
+    fn fonts() -> Fonts {
+        Fonts::new("test", [8.0, 16.0, 32.0])
+    }
+
+    #[test]
+    fn fills_the_bar_with_the_progress() {
+        let mut recorder = Recorder::new();
+        let area = Rect::new(0.0, 0.0, 400.0, 200.0);
+        render_loading(&mut recorder, &fonts(), area, 0.25, Some("sound hit"));
+        let bar = recorder.commands.iter().find_map(|command| match command {
+            Command::Rect { rect, color } if *color == BAR => Some(*rect),
+            _ => None,
+        });
+        assert_eq!(bar, Some(Rect::new(100.0, 100.0, 50.0, 8.0)));
+        assert_eq!(recorder.texts(), ["Loading sound hit"]);
+    }
+
+    #[test]
+    fn names_what_failed() {
+        let mut recorder = Recorder::new();
+        let area = Rect::new(0.0, 0.0, 400.0, 200.0);
+        let message = "could not load sound hit (/sounds/hit.wav): file not found";
+        render_error(&mut recorder, &fonts(), area, message);
+        let texts = recorder.texts().join(" ");
+        assert!(
+            texts.contains("(/sounds/hit.wav): file not found"),
+            "{}",
+            texts
+        );
+        assert!(texts.ends_with("Press Escape to quit"));
+    }
+
+    #[test]
+    fn stacks_toasts_at_the_bottom() {
+        let mut recorder = Recorder::new();
+        // 62 characters at 8 pixels measure 248 pixels, so 2 lines in 200.
+        let area = Rect::new(0.0, 0.0, 200.0, 200.0);
+        let message = "could not load image blocks (/graphics/blocks.png): truncated";
+        render_toast(&mut recorder, &fonts(), area, message, true);
+        let panel = recorder.commands.iter().find_map(|command| match command {
+            Command::Rect { rect, color } if *color == TOAST => Some(*rect),
+            _ => None,
+        });
+        assert_eq!(panel, Some(Rect::new(0.0, 184.0, 200.0, 16.0)));
+        assert_eq!(recorder.texts().len(), 2);
+    }
+}
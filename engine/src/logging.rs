@@ -0,0 +1,137 @@
+//! Logging through the `log` macros, to the standard error. What is printed
+//! is set with `--log` or the `<GAME>_LOG` variable: a level for everything,
+//! then levels for some modules, like `warn,engine::assets=debug`.
+
+use std::env;
+use std::time::Instant;
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// The levels of each module, from a spec like `info,engine::assets=debug`.
+#[derive(Debug, PartialEq)]
+pub struct Filter {
+    default: LevelFilter,
+    // Longest module first, so that the first match is the most precise.
+    modules: Vec<(String, LevelFilter)>,
+}
+
+impl Filter {
+    pub fn parse(spec: &str) -> Result<Filter, String> {
+        let mut filter = Filter {
+            default: LevelFilter::Warn,
+            modules: Vec::new(),
+        };
+        for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            let parse_level = |level: &str| {
+                level
+                    .parse()
+                    .map_err(|_| format!("unknown log level {:?}", level))
+            };
+            match directive.split_once('=') {
+                Some((module, level)) => filter
+                    .modules
+                    .push((module.to_string(), parse_level(level)?)),
+                None => filter.default = parse_level(directive)?,
+            }
+        }
+        filter
+            .modules
+            .sort_by_key(|(module, _)| std::cmp::Reverse(module.len()));
+        Ok(filter)
+    }
+
+    /// The level of the messages printed for `target`, a module path.
+    pub fn level(&self, target: &str) -> LevelFilter {
+        let within = |module: &str| {
+            target == module
+                || target.starts_with(module) && target[module.len()..].starts_with("::")
+        };
+        self.modules
+            .iter()
+            .find(|(module, _)| within(module))
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    fn max(&self) -> LevelFilter {
+        let modules = self.modules.iter().map(|(_, level)| *level);
+        modules.fold(self.default, Ord::max)
+    }
+}
+
+struct Logger {
+    filter: Filter,
+    start: Instant,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.filter.level(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            let time = self.start.elapsed().as_secs_f32();
+            let level = record.level();
+            eprintln!(
+                "{:8.3} {:<5} {}: {}",
+                time,
+                level,
+                record.target(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Starts printing the messages of the game and the engine. Without a spec
+/// from `--log` or `<GAME>_LOG`, they are printed from the info level, and
+/// only the warnings of the libraries underneath.
+pub fn init(game_id: &str, spec: Option<&str>) {
+    let var = format!("{}_LOG", game_id.to_uppercase());
+    let spec = spec
+        .map(str::to_string)
+        .or_else(|| env::var(var).ok())
+        .unwrap_or_else(|| format!("warn,engine=info,{}=info", game_id));
+    let (filter, error) = match Filter::parse(&spec) {
+        Ok(filter) => (filter, None),
+        Err(e) => (Filter::parse("info").unwrap(), Some(e)),
+    };
+    log::set_max_level(filter.max());
+    let logger = Logger {
+        filter,
+        start: Instant::now(),
+    };
+    if log::set_logger(Box::leak(Box::new(logger))).is_err() {
+        log::warn!("a logger was already set");
+    }
+    if let Some(e) = error {
+        log::warn!("ignoring log spec {:?}: {}", spec, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use log::LevelFilter;
+
+    use super::Filter;
+
+    #[test]
+    fn uses_the_most_precise_module() {
+        let filter = Filter::parse("info, engine=warn ,engine::assets=trace").unwrap();
+        assert_eq!(filter.level("pong"), LevelFilter::Info);
+        assert_eq!(filter.level("engine::audio"), LevelFilter::Warn);
+        assert_eq!(filter.level("engine::assets"), LevelFilter::Trace);
+        // Not a module of engine.
+        assert_eq!(filter.level("engineer"), LevelFilter::Info);
+        assert_eq!(filter.max(), LevelFilter::Trace);
+    }
+
+    #[test]
+    fn rejects_unknown_levels() {
+        assert!(Filter::parse("loud").is_err());
+        assert!(Filter::parse("pong=everything").is_err());
+        assert_eq!(Filter::parse("").unwrap().level("pong"), LevelFilter::Warn);
+    }
+}
@@ -0,0 +1,222 @@
+//! A `Renderer` that writes down what it is asked to draw, so that rendering
+//! can be tested without a window or a GPU.
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use ggez::graphics::{Color, Image, Rect, Text};
+
+use cgmath::{Point2, Vector2};
+
+use super::render::Renderer;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Rect {
+        rect: Rect,
+        color: Color,
+    },
+    Circle {
+        bounds: Rect,
+        color: Color,
+    },
+    Line {
+        from: Point2<f32>,
+        to: Point2<f32>,
+        width: f32,
+        color: Color,
+    },
+    Image {
+        width: u32,
+        height: u32,
+        dest: Point2<f32>,
+        scale: Vector2<f32>,
+    },
+    Text {
+        content: String,
+        size: f32,
+        pos: Point2<f32>,
+        color: Color,
+    },
+}
+
+struct Hex(Color);
+
+impl fmt::Display for Hex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (r, g, b, a) = self.0.to_rgba();
+        write!(f, "#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+    }
+}
+
+// One line per command, for snapshots.
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Command::Rect { rect, color } => write!(
+                f,
+                "rect {} {} {}x{} {}",
+                rect.x,
+                rect.y,
+                rect.w,
+                rect.h,
+                Hex(*color)
+            ),
+            Command::Circle { bounds, color } => write!(
+                f,
+                "circle {} {} {}x{} {}",
+                bounds.x,
+                bounds.y,
+                bounds.w,
+                bounds.h,
+                Hex(*color)
+            ),
+            Command::Line {
+                from,
+                to,
+                width,
+                color,
+            } => write!(
+                f,
+                "line {} {} to {} {} {} {}",
+                from.x,
+                from.y,
+                to.x,
+                to.y,
+                width,
+                Hex(*color)
+            ),
+            Command::Image {
+                width,
+                height,
+                dest,
+                scale,
+            } => write!(
+                f,
+                "image {}x{} at {} {} scaled {} {}",
+                width, height, dest.x, dest.y, scale.x, scale.y
+            ),
+            Command::Text {
+                content,
+                size,
+                pos,
+                color,
+            } => write!(
+                f,
+                "text {:?} {} at {} {} {}",
+                content,
+                size,
+                pos.x,
+                pos.y,
+                Hex(*color)
+            ),
+        }
+    }
+}
+
+/// Records draw commands. Texts are measured as if every character were
+/// half as wide as the text is high, so layouts don't depend on a font.
+#[derive(Default)]
+pub struct Recorder {
+    pub commands: Vec<Command>,
+}
+
+impl Recorder {
+    pub fn new() -> Recorder {
+        Recorder::default()
+    }
+
+    /// The contents of the recorded texts, in drawing order.
+    pub fn texts(&self) -> Vec<&str> {
+        self.commands
+            .iter()
+            .filter_map(|command| match command {
+                Command::Text { content, .. } => Some(content.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The recorded commands, one per line, to compare with a stored copy.
+    pub fn snapshot(&self) -> String {
+        let mut snapshot = String::new();
+        for command in &self.commands {
+            snapshot.push_str(&command.to_string());
+            snapshot.push('\n');
+        }
+        snapshot
+    }
+
+    /// Checks the snapshot against the copy stored at `path`. With
+    /// `UPDATE_SNAPSHOTS=1` in the environment, stores it instead.
+    pub fn assert_snapshot(&self, path: impl AsRef<Path>) {
+        let path = path.as_ref();
+        let snapshot = self.snapshot();
+        if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            if let Err(e) = fs::write(path, &snapshot) {
+                panic!("could not write {}: {}", path.display(), e);
+            }
+            return;
+        }
+        match fs::read_to_string(path) {
+            Ok(stored) => assert_eq!(snapshot, stored, "{} is out of date", path.display()),
+            Err(e) => panic!(
+                "could not read {} ({}), run with UPDATE_SNAPSHOTS=1 to create it",
+                path.display(),
+                e
+            ),
+        }
+    }
+}
+
+fn text_size(text: &Text) -> f32 {
+    match text.fragments().first().and_then(|fragment| fragment.scale) {
+        Some(scale) => scale.y,
+        None => 0.0,
+    }
+}
+
+impl Renderer for Recorder {
+    fn rect(&mut self, rect: Rect, color: Color) {
+        self.commands.push(Command::Rect { rect, color })
+    }
+
+    fn circle(&mut self, bounds: Rect, color: Color) {
+        self.commands.push(Command::Circle { bounds, color })
+    }
+
+    fn line(&mut self, from: Point2<f32>, to: Point2<f32>, width: f32, color: Color) {
+        self.commands.push(Command::Line {
+            from,
+            to,
+            width,
+            color,
+        })
+    }
+
+    fn image(&mut self, image: &Image, dest: Point2<f32>, scale: Vector2<f32>) {
+        self.commands.push(Command::Image {
+            width: image.width(),
+            height: image.height(),
+            dest,
+            scale,
+        })
+    }
+
+    fn text(&mut self, text: &Text, pos: Point2<f32>, color: Color) {
+        self.commands.push(Command::Text {
+            content: text.contents(),
+            size: text_size(text),
+            pos,
+            color,
+        })
+    }
+
+    fn measure(&self, text: &Text) -> (f32, f32) {
+        let size = text_size(text);
+        let width = text.contents().chars().count() as f32 * size / 2.0;
+        (width, size)
+    }
+}
@@ -0,0 +1,277 @@
+//! The only place that talks to ggez's graphics API. Games draw through a
+//! `Renderer`: a `Frame` when playing, so that a change in ggez only has to
+//! be followed here, or a `Recorder` in tests.
+
+use std::borrow::Cow;
+
+use ggez::graphics::{
+    self, Canvas, Color, DrawMode, DrawParam, Image, InstanceArray, Mesh, Rect, Sampler, Text,
+};
+use ggez::{Context, GameResult};
+
+use cgmath::{Point2, Vector2};
+
+use super::text;
+
+/// Where the games draw to.
+pub trait Renderer {
+    fn rect(&mut self, rect: Rect, color: Color);
+    /// Draws the ellipse inscribed in `bounds`.
+    fn circle(&mut self, bounds: Rect, color: Color);
+    fn line(&mut self, from: Point2<f32>, to: Point2<f32>, width: f32, color: Color);
+    fn image(&mut self, image: &Image, dest: Point2<f32>, scale: Vector2<f32>);
+    fn text(&mut self, text: &Text, pos: Point2<f32>, color: Color);
+    /// Size of `text` once drawn, in pixels.
+    fn measure(&self, text: &Text) -> (f32, f32);
+}
+
+/// Solid shapes queued during a frame. They are submitted as one instanced
+/// draw per kind of shape, rather than one draw (and one mesh) each.
+#[derive(Default)]
+pub struct ShapeBatch {
+    rects: Vec<DrawParam>,
+    circles: Vec<DrawParam>,
+}
+
+impl ShapeBatch {
+    pub fn new() -> ShapeBatch {
+        ShapeBatch::default()
+    }
+
+    pub fn rect(&mut self, rect: Rect, color: Color) {
+        self.rects
+            .push(DrawParam::new().dest_rect(rect).color(color));
+    }
+
+    /// Queues the ellipse inscribed in `bounds`.
+    pub fn circle(&mut self, bounds: Rect, color: Color) {
+        self.circles
+            .push(DrawParam::new().dest_rect(bounds).color(color));
+    }
+
+    /// Queues a segment `width` pixels thick, as a rotated rectangle.
+    pub fn line(&mut self, from: Point2<f32>, to: Point2<f32>, width: f32, color: Color) {
+        let (dx, dy) = (to.x - from.x, to.y - from.y);
+        let param = DrawParam::new()
+            .dest(from)
+            .rotation(dy.atan2(dx))
+            .scale(Vector2::new(dx.hypot(dy), width))
+            .offset(Point2::new(0.0, 0.5))
+            .color(color);
+        self.rects.push(param);
+    }
+
+    pub fn len(&self) -> usize {
+        self.rects.len() + self.circles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of draws submitting the queued shapes takes.
+    pub fn draw_calls(&self) -> usize {
+        usize::from(!self.rects.is_empty()) + usize::from(!self.circles.is_empty())
+    }
+
+    /// Forgets the queued shapes, keeping the memory for the next frame.
+    pub fn clear(&mut self) {
+        self.rects.clear();
+        self.circles.clear();
+    }
+}
+
+/// What was submitted to the GPU for a frame.
+#[derive(Clone, Copy, Default)]
+pub struct FrameStats {
+    pub draw_calls: usize,
+    pub shapes: usize,
+}
+
+/// The GPU side of the shape batch, kept from one frame to the next.
+pub struct Shapes {
+    batch: ShapeBatch,
+    rects: InstanceArray,
+    circles: InstanceArray,
+    // A circle of diameter 1 in the unit square, scaled by each instance.
+    circle: Mesh,
+}
+
+impl Shapes {
+    pub fn new(ctx: &Context) -> GameResult<Shapes> {
+        let circle = Mesh::new_circle(
+            ctx,
+            DrawMode::fill(),
+            Point2::new(0.5, 0.5),
+            0.5,
+            0.002,
+            Color::WHITE,
+        )?;
+        Ok(Shapes {
+            batch: ShapeBatch::new(),
+            rects: InstanceArray::new(ctx, None),
+            circles: InstanceArray::new(ctx, None),
+            circle,
+        })
+    }
+
+    // ggez uploads an instance array into a fresh buffer each time it is
+    // drawn, so the same arrays can be flushed several times in a frame.
+    fn flush(&mut self, canvas: &mut Canvas, stats: &mut FrameStats) {
+        stats.draw_calls += self.batch.draw_calls();
+        stats.shapes += self.batch.len();
+        if !self.batch.rects.is_empty() {
+            self.rects.set(self.batch.rects.drain(..));
+            canvas.draw(&self.rects, DrawParam::new());
+        }
+        if !self.batch.circles.is_empty() {
+            self.circles.set(self.batch.circles.drain(..));
+            canvas.draw_instanced_mesh(self.circle.clone(), &self.circles, DrawParam::new());
+        }
+    }
+}
+
+/// A frame being drawn on an offscreen image, in the coordinates of that
+/// image. Nothing shows until the frame is finished.
+pub struct Frame<'a> {
+    ctx: &'a mut Context,
+    canvas: Canvas,
+    shapes: Shapes,
+    stats: FrameStats,
+}
+
+impl<'a> Frame<'a> {
+    pub fn new(
+        ctx: &'a mut Context,
+        target: &Image,
+        sampler: Sampler,
+        clear: Color,
+        shapes: Shapes,
+    ) -> Frame<'a> {
+        let mut canvas = Canvas::from_image(ctx, target.clone(), clear);
+        canvas.set_sampler(sampler);
+        Frame {
+            ctx,
+            canvas,
+            shapes,
+            stats: FrameStats::default(),
+        }
+    }
+
+    /// Submits what was drawn, giving back the context and the shape batch
+    /// for the next frame.
+    pub fn finish(self) -> GameResult<(&'a mut Context, Shapes, FrameStats)> {
+        let Frame {
+            ctx,
+            mut canvas,
+            mut shapes,
+            mut stats,
+        } = self;
+        shapes.flush(&mut canvas, &mut stats);
+        canvas.finish(ctx)?;
+        Ok((ctx, shapes, stats))
+    }
+}
+
+impl<'a> Renderer for Frame<'a> {
+    fn rect(&mut self, rect: Rect, color: Color) {
+        self.shapes.batch.rect(rect, color)
+    }
+
+    fn circle(&mut self, bounds: Rect, color: Color) {
+        self.shapes.batch.circle(bounds, color)
+    }
+
+    fn line(&mut self, from: Point2<f32>, to: Point2<f32>, width: f32, color: Color) {
+        self.shapes.batch.line(from, to, width, color)
+    }
+
+    fn image(&mut self, image: &Image, dest: Point2<f32>, scale: Vector2<f32>) {
+        self.shapes.flush(&mut self.canvas, &mut self.stats);
+        let param = DrawParam::new().dest(dest).scale(scale);
+        self.canvas.draw(image, param);
+        self.stats.draw_calls += 1;
+    }
+
+    fn text(&mut self, text: &Text, pos: Point2<f32>, color: Color) {
+        self.shapes.flush(&mut self.canvas, &mut self.stats);
+        let param = DrawParam::new().dest(pos).color(color);
+        self.canvas.draw(text, param);
+        self.stats.draw_calls += 1;
+    }
+
+    fn measure(&self, text: &Text) -> (f32, f32) {
+        text::measure(self.ctx, text)
+    }
+}
+
+/// An image that can be drawn on, `width` by `height` pixels.
+pub fn target(ctx: &Context, width: u32, height: u32) -> Image {
+    let format = ctx.gfx.surface_format();
+    Image::new_canvas_image(ctx, format, width, height, 1)
+}
+
+/// Draws `image` onto the window, cleared to black first, and presents it.
+pub fn present(
+    ctx: &mut Context,
+    image: &Image,
+    sampler: Sampler,
+    dest: Point2<f32>,
+    scale: f32,
+) -> GameResult<()> {
+    composite(ctx, image, sampler, dest, scale, Some(Color::BLACK))
+}
+
+/// Draws `image` over what the window already shows.
+pub fn present_over(
+    ctx: &mut Context,
+    image: &Image,
+    sampler: Sampler,
+    dest: Point2<f32>,
+    scale: f32,
+) -> GameResult<()> {
+    composite(ctx, image, sampler, dest, scale, None)
+}
+
+fn composite(
+    ctx: &mut Context,
+    image: &Image,
+    sampler: Sampler,
+    dest: Point2<f32>,
+    scale: f32,
+    clear: Option<Color>,
+) -> GameResult<()> {
+    let mut canvas = Canvas::from_frame(ctx, clear);
+    canvas.set_sampler(sampler);
+    let param = DrawParam::new()
+        .dest(dest)
+        .scale(Vector2::new(scale, scale));
+    canvas.draw(image, param);
+    canvas.finish(ctx)
+}
+
+pub fn load_image(ctx: &Context, path: &str) -> GameResult<Image> {
+    Image::from_path(ctx, path)
+}
+
+/// Makes the font at `path` available to `Text` under `name`.
+pub fn load_font(ctx: &mut Context, name: &str, path: &str) -> GameResult<()> {
+    let data = graphics::FontData::from_path(ctx, path)?;
+    ctx.gfx.add_font(name, data);
+    Ok(())
+}
+
+/// Decodes an image file already in memory.
+pub fn decode_image(ctx: &Context, bytes: &[u8]) -> GameResult<Image> {
+    Image::from_bytes(ctx, bytes)
+}
+
+/// Makes a font file already in memory available to `Text` under `name`.
+pub fn add_font(ctx: &mut Context, name: &str, bytes: Cow<'static, [u8]>) -> GameResult<()> {
+    let data = match bytes {
+        Cow::Borrowed(bytes) => graphics::FontData::from_slice(bytes)?,
+        Cow::Owned(bytes) => graphics::FontData::from_vec(bytes)?,
+    };
+    ctx.gfx.add_font(name, data);
+    Ok(())
+}
@@ -0,0 +1,150 @@
+//! Files a game keeps between sessions, like a tournament in progress, as
+//! JSON in its data directory, or histories with one JSON object per line.
+//! Like the settings, failing to read or write them is only a warning.
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use directories::ProjectDirs;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Where the file `name` of `game_id` is kept.
+pub fn path(game_id: &str, name: &str) -> Option<PathBuf> {
+    let dirs = ProjectDirs::from("", "vinz", game_id)?;
+    Some(dirs.data_dir().join(name))
+}
+
+/// The content of the file, or `None` if it is missing or invalid.
+pub fn load<T: DeserializeOwned>(game_id: &str, name: &str) -> Option<T> {
+    read(&path(game_id, name)?)
+}
+
+pub fn store<T: Serialize>(game_id: &str, name: &str, value: &T) {
+    if let Some(path) = path(game_id, name) {
+        write(&path, value)
+    }
+}
+
+/// Every line of a history that can be read, oldest first.
+pub fn load_lines<T: DeserializeOwned>(game_id: &str, name: &str) -> Vec<T> {
+    path(game_id, name).map_or(Vec::new(), |path| read_lines(&path))
+}
+
+/// Adds a line at the end of a history.
+pub fn append<T: Serialize>(game_id: &str, name: &str, value: &T) {
+    if let Some(path) = path(game_id, name) {
+        append_line(&path, value)
+    }
+}
+
+pub fn remove(game_id: &str, name: &str) {
+    if let Some(path) = path(game_id, name) {
+        if let Err(e) = fs::remove_file(&path) {
+            log::debug!("could not remove {}: {}", path.display(), e);
+        }
+    }
+}
+
+fn read<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    let content = fs::read_to_string(path).ok()?;
+    match serde_json::from_str(&content) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            log::warn!("ignoring invalid {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+fn write<T: Serialize>(path: &Path, value: &T) {
+    let content = match serde_json::to_string_pretty(value) {
+        Ok(content) => content,
+        Err(e) => {
+            log::warn!("could not serialize {}: {}", path.display(), e);
+            return;
+        }
+    };
+    let result = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(path, content));
+    match result {
+        Ok(()) => log::debug!("saved {}", path.display()),
+        Err(e) => log::warn!("could not write {}: {}", path.display(), e),
+    }
+}
+
+fn read_lines<T: DeserializeOwned>(path: &Path) -> Vec<T> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(_) => return Vec::new(),
+    };
+    let lines = content.lines().filter(|line| !line.trim().is_empty());
+    let parsed = lines
+        .enumerate()
+        .filter_map(|(i, line)| match serde_json::from_str(line) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                log::warn!("ignoring line {} of {}: {}", i + 1, path.display(), e);
+                None
+            }
+        });
+    parsed.collect()
+}
+
+fn append_line<T: Serialize>(path: &Path, value: &T) {
+    let line = match serde_json::to_string(value) {
+        Ok(line) => line,
+        Err(e) => {
+            log::warn!("could not serialize {}: {}", path.display(), e);
+            return;
+        }
+    };
+    let result = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| {
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            writeln!(file, "{}", line)
+        });
+    if let Err(e) = result {
+        log::warn!("could not write {}: {}", path.display(), e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::fs;
+
+    use super::{append_line, read, read_lines, write};
+
+    #[test]
+    fn reads_back_what_it_wrote() {
+        let dir = std::env::temp_dir().join(format!("engine-save-{}", std::process::id()));
+        let path = dir.join("nested").join("scores.json");
+        let mut scores = BTreeMap::new();
+        let _ = scores.insert("ann".to_string(), 12u32);
+        write(&path, &scores);
+        assert_eq!(read::<BTreeMap<String, u32>>(&path), Some(scores));
+
+        fs::write(&path, "{").unwrap();
+        assert_eq!(read::<BTreeMap<String, u32>>(&path), None);
+        assert_eq!(read::<u32>(&dir.join("missing.json")), None);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn appends_lines_and_skips_broken_ones() {
+        let dir = std::env::temp_dir().join(format!("engine-history-{}", std::process::id()));
+        let path = dir.join("history.jsonl");
+        append_line(&path, &1u32);
+        fs::write(&path, fs::read_to_string(&path).unwrap() + "oops\n").unwrap();
+        append_line(&path, &2u32);
+        assert_eq!(read_lines::<u32>(&path), [1, 2]);
+        assert!(read_lines::<u32>(&dir.join("missing.jsonl")).is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,227 @@
+use std::fs;
+use std::path::PathBuf;
+
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+use ggez::conf::{FullscreenType, WindowMode, WindowSetup};
+
+pub const WINDOW_SIZES: [(f32, f32); 4] = [
+    (960.0, 540.0),
+    (1280.0, 720.0),
+    (1600.0, 900.0),
+    (1920.0, 1080.0),
+];
+
+const VOLUME_STEP: f32 = 0.1;
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub master_volume: f32,
+    pub sfx_volume: f32,
+    pub music_volume: f32,
+    pub fullscreen: bool,
+    pub vsync: bool,
+    pub show_fps: bool,
+    pub window_size: usize,
+    /// Seconds left idle on the title screen before the demo starts, or 0
+    /// for no demo.
+    pub attract_delay: f32,
+    /// The effects of `juice`, each of which can be turned off.
+    pub screen_shake: bool,
+    pub hit_flash: bool,
+    pub ball_trail: bool,
+    pub squash: bool,
+    pub score_popups: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            master_volume: 1.0,
+            sfx_volume: 1.0,
+            music_volume: 0.5,
+            fullscreen: false,
+            vsync: false,
+            show_fps: true,
+            window_size: 1,
+            attract_delay: 30.0,
+            screen_shake: true,
+            hit_flash: true,
+            ball_trail: true,
+            squash: true,
+            score_popups: true,
+        }
+    }
+}
+
+fn settings_path(game_id: &str) -> Option<PathBuf> {
+    let dirs = ProjectDirs::from("", "vinz", game_id)?;
+    Some(dirs.config_dir().join("settings.toml"))
+}
+
+impl Settings {
+    /// Reads the settings file, falling back to the defaults if it is
+    /// missing or can't be parsed.
+    pub fn load(game_id: &str) -> Settings {
+        let path = match settings_path(game_id) {
+            Some(path) => path,
+            None => return Settings::default(),
+        };
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(_) => return Settings::default(),
+        };
+        match toml::from_str(&content) {
+            Ok(settings) => settings,
+            Err(e) => {
+                log::warn!("ignoring invalid {}: {}", path.display(), e);
+                Settings::default()
+            }
+        }
+    }
+
+    pub fn save(&self, game_id: &str) {
+        let path = match settings_path(game_id) {
+            Some(path) => path,
+            None => return,
+        };
+        let content = match toml::to_string(self) {
+            Ok(content) => content,
+            Err(e) => {
+                log::warn!("could not serialize settings: {}", e);
+                return;
+            }
+        };
+        let result = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&path, content));
+        if let Err(e) = result {
+            log::warn!("could not write {}: {}", path.display(), e);
+        }
+    }
+
+    pub fn window_size(&self) -> (f32, f32) {
+        WINDOW_SIZES[self.window_size.min(WINDOW_SIZES.len() - 1)]
+    }
+
+    pub fn window_mode(&self) -> WindowMode {
+        let (width, height) = self.window_size();
+        let fullscreen_type = if self.fullscreen {
+            FullscreenType::Desktop
+        } else {
+            FullscreenType::Windowed
+        };
+        WindowMode::default()
+            .dimensions(width, height)
+            .fullscreen_type(fullscreen_type)
+            .resizable(true)
+    }
+
+    pub fn window_setup(&self, title: &str) -> WindowSetup {
+        WindowSetup::default().title(title).vsync(self.vsync)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum OptionItem {
+    MasterVolume,
+    SfxVolume,
+    MusicVolume,
+    Fullscreen,
+    Vsync,
+    ShowFps,
+    WindowSize,
+    ScreenShake,
+    HitFlash,
+    BallTrail,
+    Squash,
+    ScorePopups,
+}
+
+pub const OPTION_ITEMS: [OptionItem; 12] = [
+    OptionItem::MasterVolume,
+    OptionItem::SfxVolume,
+    OptionItem::MusicVolume,
+    OptionItem::Fullscreen,
+    OptionItem::Vsync,
+    OptionItem::ShowFps,
+    OptionItem::WindowSize,
+    OptionItem::ScreenShake,
+    OptionItem::HitFlash,
+    OptionItem::BallTrail,
+    OptionItem::Squash,
+    OptionItem::ScorePopups,
+];
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "ON"
+    } else {
+        "OFF"
+    }
+}
+
+fn volume_bar(value: f32) -> String {
+    let filled = (value * 10.0).round() as usize;
+    format!("{}{}", "|".repeat(filled), ".".repeat(10 - filled))
+}
+
+impl OptionItem {
+    pub fn label(&self, settings: &Settings) -> String {
+        match self {
+            OptionItem::MasterVolume => {
+                format!("MASTER VOLUME  {}", volume_bar(settings.master_volume))
+            }
+            OptionItem::SfxVolume => format!("SFX VOLUME  {}", volume_bar(settings.sfx_volume)),
+            OptionItem::MusicVolume => {
+                format!("MUSIC VOLUME  {}", volume_bar(settings.music_volume))
+            }
+            OptionItem::Fullscreen => format!("FULLSCREEN  {}", on_off(settings.fullscreen)),
+            OptionItem::Vsync => format!("VSYNC  {}", on_off(settings.vsync)),
+            OptionItem::ShowFps => format!("FPS COUNTER  {}", on_off(settings.show_fps)),
+            OptionItem::WindowSize => {
+                let (width, height) = settings.window_size();
+                format!("WINDOW SIZE  {}x{}", width, height)
+            }
+            OptionItem::ScreenShake => format!("SCREEN SHAKE  {}", on_off(settings.screen_shake)),
+            OptionItem::HitFlash => format!("HIT FLASH  {}", on_off(settings.hit_flash)),
+            OptionItem::BallTrail => format!("BALL TRAIL  {}", on_off(settings.ball_trail)),
+            OptionItem::Squash => format!("SQUASH  {}", on_off(settings.squash)),
+            OptionItem::ScorePopups => format!("SCORE POPUPS  {}", on_off(settings.score_popups)),
+        }
+    }
+
+    /// Changes the setting by one step, `step` being -1 or 1.
+    pub fn adjust(&self, settings: &mut Settings, step: i32) {
+        let volume = |value: f32| (value + VOLUME_STEP * step as f32).clamp(0.0, 1.0);
+        match self {
+            OptionItem::MasterVolume => settings.master_volume = volume(settings.master_volume),
+            OptionItem::SfxVolume => settings.sfx_volume = volume(settings.sfx_volume),
+            OptionItem::MusicVolume => settings.music_volume = volume(settings.music_volume),
+            OptionItem::Fullscreen => settings.fullscreen = !settings.fullscreen,
+            OptionItem::Vsync => settings.vsync = !settings.vsync,
+            OptionItem::ShowFps => settings.show_fps = !settings.show_fps,
+            OptionItem::WindowSize => {
+                let len = WINDOW_SIZES.len() as i32;
+                let index = settings.window_size.min(WINDOW_SIZES.len() - 1) as i32;
+                settings.window_size = (index + step).rem_euclid(len) as usize
+            }
+            OptionItem::ScreenShake => settings.screen_shake = !settings.screen_shake,
+            OptionItem::HitFlash => settings.hit_flash = !settings.hit_flash,
+            OptionItem::BallTrail => settings.ball_trail = !settings.ball_trail,
+            OptionItem::Squash => settings.squash = !settings.squash,
+            OptionItem::ScorePopups => settings.score_popups = !settings.score_popups,
+        }
+    }
+
+    /// Whether the change only takes effect on the next launch.
+    pub fn needs_restart(&self) -> bool {
+        matches!(
+            self,
+            OptionItem::Fullscreen | OptionItem::Vsync | OptionItem::WindowSize
+        )
+    }
+}
@@ -0,0 +1,80 @@
+use ggez::{Context, GameResult};
+use std::collections::HashMap;
+
+use super::render::Renderer;
+
+/// Identifies a state of a `StateMachine`, and carries the parameters it is
+/// entered with.
+pub trait StateKind {
+    fn key(&self) -> String;
+}
+
+/// A screen of the game. `D` is the game data shared by all the states.
+pub trait State<K, D> {
+    fn enter(&mut self, params: K);
+    fn exit(&self);
+    fn update(&mut self, data: &mut D, ctx: &mut Context) -> GameResult<Option<K>>;
+    fn render(&self, data: &D, renderer: &mut dyn Renderer) -> GameResult<()>;
+}
+
+pub struct StateMachine<K, D> {
+    pub states: HashMap<String, Box<dyn State<K, D>>>,
+    current: Option<String>,
+}
+
+impl<K: StateKind, D> StateMachine<K, D> {
+    pub fn new() -> StateMachine<K, D> {
+        StateMachine {
+            states: HashMap::new(),
+            current: None,
+        }
+    }
+
+    pub fn add(&mut self, key: &str, state: Box<dyn State<K, D>>) {
+        let _ = self.states.insert(key.to_string(), state);
+    }
+
+    pub fn current(&self) -> Option<&str> {
+        self.current.as_deref()
+    }
+
+    pub fn change(&mut self, params: K) {
+        if let Some(key) = &self.current {
+            self.states[key].exit()
+        }
+        let key = params.key();
+        log::info!("entering state {}", key);
+        let current = self.states.get_mut(&key).unwrap();
+        current.enter(params);
+        self.current = Some(key)
+    }
+
+    pub fn render(&self, data: &D, renderer: &mut dyn Renderer) -> GameResult<()> {
+        match &self.current {
+            Some(key) => {
+                let current_state = &self.states[key];
+                current_state.render(data, renderer)
+            }
+            None => Ok(()),
+        }
+    }
+
+    pub fn update(&mut self, data: &mut D, ctx: &mut Context) -> GameResult {
+        match &mut self.current {
+            Some(key) => {
+                let current_state = self.states.get_mut(key).unwrap();
+                if let Some(new_state) = current_state.update(data, ctx)? {
+                    self.change(new_state)
+                }
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl<K: StateKind, D> Default for StateMachine<K, D> {
+    fn default() -> StateMachine<K, D> {
+        StateMachine::new()
+    }
+}
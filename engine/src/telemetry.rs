@@ -0,0 +1,120 @@
+//! Event logs for balancing the games. With `--telemetry`, every event a game
+//! records goes to a new file per session, one JSON object per line with the
+//! seconds since the start in `t`, e.g.
+//! `{"t":12.5,"event":"hit","player":1,"x":45.0,"y":300.0,"speed":230.0}`.
+
+use std::fs::{self, File};
+use std::io::{LineWriter, Write};
+use std::path::PathBuf;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use directories::ProjectDirs;
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct Line<'a, E> {
+    t: f64,
+    #[serde(flatten)]
+    event: &'a E,
+}
+
+// A line of the log, `t` rounded to the millisecond.
+fn line<E: Serialize>(t: f64, event: &E) -> serde_json::Result<String> {
+    let t = (t * 1000.0).round() / 1000.0;
+    serde_json::to_string(&Line { t, event })
+}
+
+/// Where the sessions of `game_id` are written.
+pub fn sessions_dir(game_id: &str) -> Option<PathBuf> {
+    let dirs = ProjectDirs::from("", "vinz", game_id)?;
+    Some(dirs.data_dir().join("sessions"))
+}
+
+/// The event log of a session, which records nothing unless started.
+pub struct Telemetry {
+    file: Option<LineWriter<File>>,
+    start: Instant,
+}
+
+impl Telemetry {
+    pub fn disabled() -> Telemetry {
+        Telemetry {
+            file: None,
+            start: Instant::now(),
+        }
+    }
+
+    /// Opens a new file in the sessions directory, named after the time.
+    /// Telemetry is only a warning away from disabled if that fails.
+    pub fn start(game_id: &str) -> Telemetry {
+        let dir = match sessions_dir(game_id) {
+            Some(dir) => dir,
+            None => {
+                log::warn!("no data directory to write telemetry to");
+                return Telemetry::disabled();
+            }
+        };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH);
+        let path = dir.join(format!("{}.jsonl", now.map_or(0, |now| now.as_secs())));
+        match fs::create_dir_all(&dir).and_then(|_| File::create(&path)) {
+            Ok(file) => {
+                log::info!("writing telemetry to {}", path.display());
+                Telemetry {
+                    file: Some(LineWriter::new(file)),
+                    start: Instant::now(),
+                }
+            }
+            Err(e) => {
+                log::warn!("could not create {}: {}", path.display(), e);
+                Telemetry::disabled()
+            }
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.file.is_some()
+    }
+
+    /// Writes `event`, an enum tagged with `#[serde(tag = "event")]`. The
+    /// first error stops the recording, rather than failing the game.
+    pub fn record<E: Serialize>(&mut self, event: &E) {
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => return,
+        };
+        let t = self.start.elapsed().as_secs_f64();
+        let result = line(t, event)
+            .map_err(|e| e.to_string())
+            .and_then(|line| writeln!(file, "{}", line).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            log::warn!("stopping telemetry: {}", e);
+            self.file = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+
+    use super::line;
+
+    #[derive(Serialize)]
+    #[serde(tag = "event", rename_all = "snake_case")]
+    enum Event {
+        Hit { player: u32, speed: f32 },
+        GameOver,
+    }
+
+    #[test]
+    fn writes_the_time_then_the_event() {
+        let hit = Event::Hit {
+            player: 2,
+            speed: 180.5,
+        };
+        let expected = r#"{"t":1.235,"event":"hit","player":2,"speed":180.5}"#;
+        assert_eq!(line(1.23456, &hit).unwrap(), expected);
+        let expected = r#"{"t":60.0,"event":"game_over"}"#;
+        assert_eq!(line(60.0, &Event::GameOver).unwrap(), expected);
+    }
+}
@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+
+use ggez::graphics::{Color, Drawable, Rect, Text, TextFragment};
+use ggez::Context;
+
+use cgmath::Point2;
+
+use super::assets::Fonts;
+use super::render::Renderer;
+
+#[derive(Clone, Copy)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+// A table column, `x` being its offset from the start of the row.
+pub struct Column {
+    pub x: f32,
+    pub width: f32,
+    pub align: Align,
+}
+
+/// Size of the text once rendered, in pixels.
+pub fn measure(ctx: &Context, text: &Text) -> (f32, f32) {
+    match text.dimensions(ctx) {
+        Some(dimensions) => (dimensions.w, dimensions.h),
+        None => (0.0, 0.0),
+    }
+}
+
+fn aligned_x(width: f32, area: Rect, align: Align) -> f32 {
+    match align {
+        Align::Left => area.x,
+        Align::Center => (area.x + (area.w - width) / 2.0).round(),
+        Align::Right => area.x + area.w - width,
+    }
+}
+
+/// Draws `text` on the top line of `area`, aligned horizontally within it.
+pub fn draw_aligned(
+    renderer: &mut dyn Renderer,
+    text: &Text,
+    area: Rect,
+    align: Align,
+    color: Color,
+) {
+    let (width, _) = renderer.measure(text);
+    let pos = Point2::new(aligned_x(width, area, align), area.y);
+    renderer.text(text, pos, color)
+}
+
+/// Splits `content` into lines no wider than `max_width`, breaking between
+/// words. A single word wider than `max_width` gets a line of its own.
+pub fn wrap(
+    renderer: &dyn Renderer,
+    content: &str,
+    fonts: &Fonts,
+    size: f32,
+    max_width: f32,
+) -> Vec<Text> {
+    let mut lines = vec![];
+    let mut current = String::new();
+    for word in content.split_whitespace() {
+        let candidate = if current.is_empty() {
+            word.to_string()
+        } else {
+            format!("{} {}", current, word)
+        };
+        let (width, _) = renderer.measure(&fonts.text(candidate.as_str(), size));
+        if width > max_width && !current.is_empty() {
+            lines.push(fonts.text(current, size));
+            current = word.to_string();
+        } else {
+            current = candidate;
+        }
+    }
+    if !current.is_empty() {
+        lines.push(fonts.text(current, size));
+    }
+    lines
+}
+
+/// Word-wraps `content` to the width of `area` and draws the lines from its
+/// top, each one aligned within the area.
+pub fn draw_wrapped(
+    renderer: &mut dyn Renderer,
+    content: &str,
+    fonts: &Fonts,
+    size: f32,
+    area: Rect,
+    align: Align,
+    color: Color,
+) {
+    let mut line_area = area;
+    for line in wrap(renderer, content, fonts, size, area.w) {
+        draw_aligned(renderer, &line, line_area, align, color);
+        let (_, height) = renderer.measure(&line);
+        line_area.y += height;
+    }
+}
+
+/// Draws one row of a table, `cells[i]` being aligned within `columns[i]`.
+pub fn draw_row(
+    renderer: &mut dyn Renderer,
+    cells: &[Text],
+    columns: &[Column],
+    pos: Point2<f32>,
+    color: Color,
+) {
+    for (cell, column) in cells.iter().zip(columns) {
+        let area = Rect::new(pos.x + column.x, pos.y, column.width, 0.0);
+        draw_aligned(renderer, cell, area, column.align, color);
+    }
+}
+
+struct Cached {
+    content: String,
+    size: f32,
+    text: Text,
+}
+
+/// Texts drawn every frame but rarely changing, such as a score. Each slot
+/// keeps its `Text` until the formatted content or size differs.
+pub struct TextCache {
+    font: String,
+    slots: HashMap<&'static str, Cached>,
+    scratch: String,
+    layouts: usize,
+}
+
+impl TextCache {
+    pub fn new(fonts: &Fonts) -> TextCache {
+        TextCache {
+            font: fonts.font.clone(),
+            slots: HashMap::new(),
+            scratch: String::new(),
+            layouts: 0,
+        }
+    }
+
+    /// The text of `slot`, laid out again only if `content` changed, e.g.
+    /// `cache.get("score", format_args!("{}", score), fonts.large)`.
+    pub fn get(&mut self, slot: &'static str, content: fmt::Arguments, size: f32) -> &Text {
+        self.scratch.clear();
+        // Writing into a String can't fail.
+        let _ = self.scratch.write_fmt(content);
+
+        let stale = match self.slots.get(slot) {
+            Some(cached) => cached.content != self.scratch || cached.size != size,
+            None => true,
+        };
+        if stale {
+            self.layouts += 1;
+            let fragment = TextFragment::new(self.scratch.as_str());
+            let text = Text::new(fragment.font(&self.font).scale(size));
+            match self.slots.get_mut(slot) {
+                Some(cached) => {
+                    cached.content.clear();
+                    cached.content.push_str(&self.scratch);
+                    cached.size = size;
+                    cached.text = text;
+                }
+                None => {
+                    let content = self.scratch.clone();
+                    let _ = self.slots.insert(
+                        slot,
+                        Cached {
+                            content,
+                            size,
+                            text,
+                        },
+                    );
+                }
+            }
+        }
+        &self.slots[&slot].text
+    }
+
+    /// How many times a text was laid out, since the cache was created.
+    pub fn layouts(&self) -> usize {
+        self.layouts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ggez::graphics::{Color, Rect};
+
+    use super::super::assets::Fonts;
+    use super::super::record::{Command, Recorder};
+    use super::{draw_aligned, draw_wrapped, wrap, Align, TextCache};
+
+    fn fonts() -> Fonts {
+        Fonts {
+            font: "test".to_string(),
+            small: 8.0,
+            medium: 16.0,
+            large: 32.0,
+        }
+    }
+
+    fn positions(recorder: &Recorder) -> Vec<(f32, f32)> {
+        recorder
+            .commands
+            .iter()
+            .map(|command| match command {
+                Command::Text { pos, .. } => (pos.x, pos.y),
+                command => panic!("expected a text, got {}", command),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn aligns_within_the_area() {
+        let fonts = fonts();
+        // 4 characters at 16 pixels measure 32 pixels wide.
+        let text = fonts.text("ABCD", 16.0);
+        let area = Rect::new(10.0, 20.0, 100.0, 0.0);
+        let mut recorder = Recorder::new();
+        for &align in &[Align::Left, Align::Center, Align::Right] {
+            draw_aligned(&mut recorder, &text, area, align, Color::WHITE);
+        }
+        let expected = vec![(10.0, 20.0), (44.0, 20.0), (78.0, 20.0)];
+        assert_eq!(positions(&recorder), expected);
+    }
+
+    #[test]
+    fn wraps_between_words() {
+        let fonts = fonts();
+        let recorder = Recorder::new();
+        // At 8 pixels a character is 4 wide, so 10 characters fit in 40.
+        let lines = wrap(&recorder, "one two three fourteen", &fonts, 8.0, 40.0);
+        let lines: Vec<String> = lines.iter().map(|line| line.contents()).collect();
+        assert_eq!(lines, ["one two", "three", "fourteen"]);
+    }
+
+    #[test]
+    fn a_long_word_gets_its_own_line() {
+        let fonts = fonts();
+        let recorder = Recorder::new();
+        let lines = wrap(&recorder, "a unbreakable b", &fonts, 8.0, 20.0);
+        let lines: Vec<String> = lines.iter().map(|line| line.contents()).collect();
+        assert_eq!(lines, ["a", "unbreakable", "b"]);
+    }
+
+    #[test]
+    fn stacks_wrapped_lines() {
+        let fonts = fonts();
+        let mut recorder = Recorder::new();
+        let area = Rect::new(0.0, 100.0, 40.0, 0.0);
+        let content = "one two three";
+        draw_wrapped(
+            &mut recorder,
+            content,
+            &fonts,
+            8.0,
+            area,
+            Align::Left,
+            Color::WHITE,
+        );
+        assert_eq!(positions(&recorder), vec![(0.0, 100.0), (0.0, 108.0)]);
+    }
+
+    #[test]
+    fn lays_out_again_only_on_change() {
+        let mut cache = TextCache::new(&fonts());
+        for score in &[0, 0, 0, 1, 1, 2] {
+            let text = cache.get("score", format_args!("{}", score), 32.0);
+            assert_eq!(text.contents(), score.to_string());
+        }
+        assert_eq!(cache.layouts(), 3);
+        let _ = cache.get("score", format_args!("{}", 2), 16.0);
+        assert_eq!(cache.layouts(), 4);
+    }
+}
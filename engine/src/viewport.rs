@@ -0,0 +1,113 @@
+use ggez::graphics::{Color, Image, Sampler};
+use ggez::{Context, GameResult};
+
+use cgmath::{Point2, Vector2};
+
+use super::render::{self, Frame, FrameStats, Shapes};
+
+// Maps the fixed virtual canvas onto the window. Whenever the window is at
+// least as large as the canvas the scale is an integer, so that pixel art
+// stays crisp, and the remaining space is left as black bars.
+pub struct Viewport {
+    pub width: f32,
+    pub height: f32,
+    pub scale: f32,
+    pub offset: Point2<f32>,
+}
+
+impl Viewport {
+    pub fn new(width: f32, height: f32, window_width: f32, window_height: f32) -> Viewport {
+        let fit = (window_width / width).min(window_height / height);
+        let scale = if fit >= 1.0 { fit.floor() } else { fit };
+        let offset = Point2::new(
+            ((window_width - width * scale) / 2.0).floor(),
+            ((window_height - height * scale) / 2.0).floor(),
+        );
+        Viewport {
+            width,
+            height,
+            scale,
+            offset,
+        }
+    }
+
+    /// Converts a window position (e.g. the mouse) into virtual coordinates.
+    pub fn to_virtual(&self, x: f32, y: f32) -> Point2<f32> {
+        Point2::new(
+            (x - self.offset.x) / self.scale,
+            (y - self.offset.y) / self.scale,
+        )
+    }
+}
+
+/// The virtual canvas a game draws on, through the frame returned by `begin`.
+pub struct Screen {
+    image: Image,
+    sampler: Sampler,
+    // Lent to the frame between `begin` and `end`.
+    shapes: Option<Shapes>,
+    stats: FrameStats,
+    pub viewport: Viewport,
+    /// Moves the canvas in the window, to shake the screen.
+    pub shake: Vector2<f32>,
+}
+
+impl Screen {
+    pub fn new(ctx: &mut Context, width: f32, height: f32, pixel_art: bool) -> GameResult<Screen> {
+        let image = render::target(ctx, width as u32, height as u32);
+        let sampler = if pixel_art {
+            Sampler::nearest_clamp()
+        } else {
+            Sampler::linear_clamp()
+        };
+        let (window_width, window_height) = ctx.gfx.drawable_size();
+        let viewport = Viewport::new(width, height, window_width, window_height);
+        Ok(Screen {
+            image,
+            sampler,
+            shapes: Some(Shapes::new(ctx)?),
+            stats: FrameStats::default(),
+            viewport,
+            shake: Vector2::new(0.0, 0.0),
+        })
+    }
+
+    pub fn resize(&mut self, window_width: f32, window_height: f32) {
+        let (width, height) = (self.viewport.width, self.viewport.height);
+        self.viewport = Viewport::new(width, height, window_width, window_height);
+    }
+
+    /// What the last frame submitted.
+    pub fn stats(&self) -> FrameStats {
+        self.stats
+    }
+
+    /// Starts drawing a frame on the canvas, in virtual coordinates.
+    pub fn begin<'a>(&mut self, ctx: &'a mut Context, clear: Color) -> GameResult<Frame<'a>> {
+        let shapes = match self.shapes.take() {
+            Some(shapes) => shapes,
+            None => Shapes::new(ctx)?,
+        };
+        Ok(Frame::new(ctx, &self.image, self.sampler, clear, shapes))
+    }
+
+    /// Scales the canvas onto the window, the uncovered borders staying black,
+    /// and presents the frame.
+    pub fn end(&mut self, frame: Frame) -> GameResult<()> {
+        let (ctx, shapes, stats) = frame.finish()?;
+        self.shapes = Some(shapes);
+        self.stats = stats;
+        let (offset, scale) = (self.viewport.offset + self.shake, self.viewport.scale);
+        render::present(ctx, &self.image, self.sampler, offset, scale)
+    }
+
+    /// Like `end`, but over what another screen presented this frame. The
+    /// frame should have been cleared to a transparent color.
+    pub fn end_over(&mut self, frame: Frame) -> GameResult<()> {
+        let (ctx, shapes, stats) = frame.finish()?;
+        self.shapes = Some(shapes);
+        self.stats = stats;
+        let (offset, scale) = (self.viewport.offset, self.viewport.scale);
+        render::present_over(ctx, &self.image, self.sampler, offset, scale)
+    }
+}
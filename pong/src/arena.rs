@@ -0,0 +1,207 @@
+//! The courts pong can be played in, from `arenas.toml`: blocks in the way,
+//! barriers going back and forth, goals narrower than the back walls, and
+//! top and bottom edges that balls go through to come back on the other
+//! side.
+
+use std::f32::consts::TAU;
+
+use serde::Deserialize;
+
+use ggez::graphics::{Color, Rect};
+
+use engine::render::Renderer;
+
+use super::paddle::Axis;
+use super::side::Side;
+use super::{VIRTUAL_HEIGHT, VIRTUAL_WIDTH};
+
+const COLOR: Color = Color::new(0.6, 0.6, 0.6, 1.0);
+
+/// A rectangle of the court, in pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Block {
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32,
+}
+
+impl Block {
+    fn rect(&self) -> Rect {
+        Rect::new(self.x, self.y, self.w, self.h)
+    }
+}
+
+/// A block going from where it is by `travel` and back, every `period`
+/// seconds.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Barrier {
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32,
+    travel: [f32; 2],
+    period: f32,
+}
+
+impl Barrier {
+    fn rect(&self, time: f32) -> Rect {
+        // Eases in and out at both ends.
+        let along = (1.0 - (TAU * time / self.period).cos()) / 2.0;
+        let (dx, dy) = (self.travel[0] * along, self.travel[1] * along);
+        Rect::new(self.x + dx, self.y + dy, self.w, self.h)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Arena {
+    pub name: String,
+    blocks: Vec<Block>,
+    barriers: Vec<Barrier>,
+    // The part of each back wall, around its middle, where balls score.
+    goal: f32,
+    /// Whether balls going through the top come back from the bottom, and
+    /// the other way around, instead of bouncing.
+    pub wrap: bool,
+    // Seconds of play, to move the barriers.
+    #[serde(skip)]
+    time: f32,
+}
+
+impl Default for Arena {
+    /// The court without anything in it.
+    fn default() -> Arena {
+        Arena {
+            name: "classic".to_string(),
+            blocks: Vec::new(),
+            barriers: Vec::new(),
+            goal: 1.0,
+            wrap: false,
+            time: 0.0,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct File {
+    arena: Vec<Arena>,
+}
+
+/// The arenas of the file, in order.
+pub fn parse(content: &str) -> Result<Vec<Arena>, String> {
+    let file: File = toml::from_str(content).map_err(|e| e.to_string())?;
+    if file.arena.is_empty() {
+        return Err("there is no arena".to_string());
+    }
+    for arena in &file.arena {
+        if arena.goal <= 0.0 || arena.goal > 1.0 {
+            return Err(format!("{}: the goal must be within 0 and 1", arena.name));
+        }
+        if arena.barriers.iter().any(|barrier| barrier.period <= 0.0) {
+            return Err(format!("{}: periods must be positive", arena.name));
+        }
+    }
+    Ok(file.arena)
+}
+
+impl Arena {
+    pub fn update(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    /// What balls bounce off in the court, where it is now.
+    pub fn obstacles(&self) -> Vec<Rect> {
+        let blocks = self.blocks.iter().map(Block::rect);
+        let barriers = self.barriers.iter().map(|barrier| barrier.rect(self.time));
+        blocks.chain(barriers).collect()
+    }
+
+    /// The parts of `wall`, along the back wall of `side`, on each side of
+    /// the goal. None when the whole wall scores.
+    pub fn posts(&self, side: Side, wall: Rect) -> Vec<Rect> {
+        if self.goal >= 1.0 {
+            return Vec::new();
+        }
+        let (start, end, court) = match side.axis() {
+            Axis::Vertical => (wall.y, wall.bottom(), VIRTUAL_HEIGHT),
+            Axis::Horizontal => (wall.x, wall.right(), VIRTUAL_WIDTH),
+        };
+        // The goal is in the middle of the court, whatever the wall.
+        let half = self.goal * court / 2.0;
+        let (goal_start, goal_end) = (court / 2.0 - half, court / 2.0 + half);
+        let (first, second) = (goal_start - start, end - goal_end);
+        match side.axis() {
+            Axis::Vertical => vec![
+                Rect::new(wall.x, start, wall.w, first),
+                Rect::new(wall.x, goal_end, wall.w, second),
+            ],
+            Axis::Horizontal => vec![
+                Rect::new(start, wall.y, first, wall.h),
+                Rect::new(goal_end, wall.y, second, wall.h),
+            ],
+        }
+    }
+
+    pub fn render(&self, renderer: &mut dyn Renderer) {
+        for obstacle in self.obstacles() {
+            renderer.rect(obstacle, COLOR);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ggez::graphics::Rect;
+
+    use super::super::side::Side;
+    use super::{parse, Arena};
+
+    const ARENAS: &str = r#"
+        [[arena]]
+        name = "classic"
+
+        [[arena]]
+        name = "narrow"
+        goal = 0.5
+        wrap = true
+        blocks = [{ x = 600, y = 100, w = 80, h = 100 }]
+        barriers = [{ x = 400, y = 0, w = 20, h = 100, travel = [0, 200], period = 4 }]
+    "#;
+
+    #[test]
+    fn reads_the_arenas_in_order() {
+        let arenas = parse(ARENAS).unwrap();
+        assert_eq!(arenas[0], Arena::default());
+        assert_eq!(arenas[1].name, "narrow");
+        assert!(arenas[1].wrap);
+
+        assert!(parse("[[arena]]\nname = \"open\"\ngoal = 0\n").is_err());
+        assert!(parse("[[arena]]\nname = \"open\"\nholes = 3\n").is_err());
+        assert!(parse("arena = []\n").is_err());
+    }
+
+    #[test]
+    fn moves_barriers_and_narrows_goals() {
+        let mut arena = parse(ARENAS).unwrap().remove(1);
+        let block = Rect::new(600.0, 100.0, 80.0, 100.0);
+        assert_eq!(
+            arena.obstacles(),
+            [block, Rect::new(400.0, 0.0, 20.0, 100.0)]
+        );
+        arena.update(2.0);
+        assert_eq!(arena.obstacles()[1], Rect::new(400.0, 200.0, 20.0, 100.0));
+
+        // Half the height of the court, in its middle.
+        let wall = Rect::new(-100.0, -100.0, 100.0, 920.0);
+        let posts = [
+            Rect::new(-100.0, -100.0, 100.0, 280.0),
+            Rect::new(-100.0, 540.0, 100.0, 280.0),
+        ];
+        assert_eq!(arena.posts(Side::Left, wall), posts);
+        assert!(Arena::default().posts(Side::Left, wall).is_empty());
+    }
+}
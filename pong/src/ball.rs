@@ -1,16 +1,24 @@
 use ggez::graphics;
-use ggez::graphics::DrawParam;
-use ggez::Context;
-use ggez::GameResult;
 
-use cgmath::{Point2, Vector2};
+use engine::debug::Inspect;
+use engine::render::Renderer;
+
+use cgmath::{InnerSpace, Point2, Vector2};
 
 use super::paddle::Paddle;
+use super::side::Side;
+use super::{VIRTUAL_HEIGHT, VIRTUAL_WIDTH};
 
+#[derive(Clone)]
 pub struct Ball {
     pub xy: Point2<f32>,
     size: Point2<f32>,
     pub delta: Vector2<f32>,
+    /// The index of the player who hit it last, if anyone did since the
+    /// serve.
+    pub last_hit: Option<usize>,
+    /// How fast it turns, in radians per second, after a hit with a curve.
+    pub curve: f32,
 }
 
 impl Ball {
@@ -23,34 +31,208 @@ impl Ball {
             return false;
         }
 
-        return true;
+        true
+    }
+
+    /// Sends the ball back into the court, a little faster, after it hit
+    /// the paddle of `side`.
+    pub fn deflect(&mut self, side: Side, paddle: &Paddle) {
+        match side {
+            Side::Left => self.xy.x = paddle.xy.x + paddle.size.x,
+            Side::Right => self.xy.x = paddle.xy.x - self.size.x,
+            Side::Top => self.xy.y = paddle.xy.y + paddle.size.y,
+            Side::Bottom => self.xy.y = paddle.xy.y - self.size.y,
+        }
+        self.turn_inward(side, 1.03)
+    }
+
+    /// Pushes the ball out of `rect` if it went into it, on the side it
+    /// went the least into, and bounces it off if it was going further in.
+    /// Gives the direction it bounced towards.
+    pub fn bounce_off(&mut self, rect: graphics::Rect) -> Option<Vector2<f32>> {
+        let (right, bottom) = (self.xy.x + self.size.x, self.xy.y + self.size.y);
+        if right < rect.x
+            || self.xy.x > rect.right()
+            || bottom < rect.y
+            || self.xy.y > rect.bottom()
+        {
+            return None;
+        }
+        let depths = [
+            (right - rect.x, Vector2::new(-1.0, 0.0)),
+            (rect.right() - self.xy.x, Vector2::new(1.0, 0.0)),
+            (bottom - rect.y, Vector2::new(0.0, -1.0)),
+            (rect.bottom() - self.xy.y, Vector2::new(0.0, 1.0)),
+        ];
+        let mut out = depths[0];
+        for &depth in &depths[1..] {
+            if depth.0 < out.0 {
+                out = depth;
+            }
+        }
+        let (depth, normal) = out;
+        self.xy += normal * depth;
+        let into = self.delta.dot(normal);
+        if into >= 0.0 {
+            return None;
+        }
+        self.delta -= normal * (2.0 * into);
+        Some(normal)
+    }
+
+    /// Brings the ball back from the opposite edge once it went all the
+    /// way through the one of `side`.
+    pub fn wrap(&mut self, side: Side) {
+        match side {
+            Side::Left if self.xy.x < -self.size.x => self.xy.x = VIRTUAL_WIDTH,
+            Side::Right if self.xy.x > VIRTUAL_WIDTH => self.xy.x = -self.size.x,
+            Side::Top if self.xy.y < -self.size.y => self.xy.y = VIRTUAL_HEIGHT,
+            Side::Bottom if self.xy.y > VIRTUAL_HEIGHT => self.xy.y = -self.size.y,
+            _ => {}
+        }
+    }
+
+    /// Whether the ball went past the wall of `side`.
+    pub fn out(&self, side: Side) -> bool {
+        match side {
+            Side::Left => self.xy.x < 0.0,
+            Side::Right => self.xy.x > VIRTUAL_WIDTH,
+            Side::Top => self.xy.y < 0.0,
+            Side::Bottom => self.xy.y > VIRTUAL_HEIGHT,
+        }
+    }
+
+    // Away from `side`, scaling the speed away from it by `factor`.
+    fn turn_inward(&mut self, side: Side, factor: f32) {
+        let inward = side.inward();
+        let speed = self.delta.dot(inward).abs() * factor;
+        self.delta = side.across() * self.delta.dot(side.across()) + inward * speed;
     }
 
     pub fn new() -> Ball {
-        let xy = Point2::new(super::WIDTH / 2.0 - 6.0, super::HEIGHT / 2.0 - 6.0);
+        let xy = Point2::new(
+            super::VIRTUAL_WIDTH / 2.0 - 6.0,
+            super::VIRTUAL_HEIGHT / 2.0 - 6.0,
+        );
 
         let size = Point2::new(12.0, 12.0);
         let delta = Vector2::new(0.0, 0.0);
-        Ball { xy, size, delta }
+        Ball {
+            xy,
+            size,
+            delta,
+            last_hit: None,
+            curve: 0.0,
+        }
     }
 
     pub fn reset(&mut self) {
-        self.xy.x = super::WIDTH / 2.0 - 6.0;
-        self.xy.y = super::HEIGHT / 2.0 - 6.0;
+        self.xy.x = super::VIRTUAL_WIDTH / 2.0 - 6.0;
+        self.xy.y = super::VIRTUAL_HEIGHT / 2.0 - 6.0;
         self.size.x = 12.0;
         self.size.y = 12.0;
         self.delta.x = 0.0;
         self.delta.y = 0.0;
+        self.last_hit = None;
+        self.curve = 0.0;
     }
 
     pub fn update(&mut self, dt: f32) {
-        self.xy = self.xy + dt * self.delta
+        if self.curve != 0.0 {
+            // Turns less and less, so that it still goes across the court.
+            let (sin, cos) = (self.curve * dt).sin_cos();
+            let Vector2 { x, y } = self.delta;
+            self.delta = Vector2::new(x * cos - y * sin, x * sin + y * cos);
+            self.curve *= (-2.0 * dt).exp();
+        }
+        self.xy += dt * self.delta
     }
 
-    pub fn render(&self, ctx: &mut Context) -> GameResult<()> {
-        let rect = graphics::Rect::new(self.xy.x, self.xy.y, self.size.x, self.size.y);
-        let mesh =
-            graphics::Mesh::new_rectangle(ctx, graphics::DrawMode::fill(), rect, graphics::Color::WHITE)?;
-        graphics::draw(ctx, &mesh, DrawParam::default())
+    /// Drawn scaled around its middle, e.g. to squash it on a bounce.
+    pub fn render(&self, renderer: &mut dyn Renderer, scale: Vector2<f32>) {
+        let (w, h) = (self.size.x * scale.x, self.size.y * scale.y);
+        let x = self.xy.x + (self.size.x - w) / 2.0;
+        let y = self.xy.y + (self.size.y - h) / 2.0;
+        renderer.rect(graphics::Rect::new(x, y, w, h), graphics::Color::WHITE)
+    }
+}
+
+impl Inspect for Ball {
+    fn hitbox(&self) -> graphics::Rect {
+        graphics::Rect::new(self.xy.x, self.xy.y, self.size.x, self.size.y)
+    }
+
+    fn velocity(&self) -> Option<Vector2<f32>> {
+        Some(self.delta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ggez::graphics::{Color, Rect};
+
+    use cgmath::{Point2, Vector2};
+
+    use engine::record::{Command, Recorder};
+
+    use super::super::paddle::Axis;
+    use super::super::side::Side;
+    use super::Ball;
+
+    #[test]
+    fn renders_as_a_white_square() {
+        let mut ball = Ball::new();
+        ball.xy.x = 100.0;
+        ball.xy.y = 50.0;
+        let mut recorder = Recorder::new();
+        ball.render(&mut recorder, Vector2::new(1.0, 1.0));
+        let square = Command::Rect {
+            rect: Rect::new(100.0, 50.0, 12.0, 12.0),
+            color: Color::WHITE,
+        };
+        assert_eq!(recorder.commands, [square]);
+    }
+
+    #[test]
+    fn goes_back_the_way_it_came_from_each_side() {
+        let mut ball = Ball::new();
+        let top = Side::Top.paddle();
+        ball.xy.y = 40.0;
+        ball.delta.x = 30.0;
+        ball.delta.y = -100.0;
+        assert!(ball.collides(&top));
+        ball.deflect(Side::Top, &top);
+        assert_eq!(ball.xy.y, top.xy.y + 15.0);
+        assert_eq!(ball.delta.x, 30.0);
+        assert!((ball.delta.y - 103.0).abs() < 1e-3);
+        assert_eq!(top.axis, Axis::Horizontal);
+
+        ball.xy.x = 1280.0;
+        let inward = Side::Right.inward();
+        assert_eq!(ball.bounce_off(Side::Right.boundary()), Some(inward));
+        assert_eq!(ball.xy.x, 1280.0 - 12.0);
+        assert_eq!(ball.delta.x, -30.0);
+        assert_eq!(ball.bounce_off(Side::Left.boundary()), None);
+        assert!(!ball.out(Side::Right));
+    }
+
+    #[test]
+    fn bounces_off_blocks_and_wraps_around() {
+        let mut ball = Ball::new();
+        ball.xy = Point2::new(95.0, 204.0);
+        ball.delta = Vector2::new(100.0, 20.0);
+        let block = Rect::new(100.0, 100.0, 50.0, 200.0);
+        assert_eq!(ball.bounce_off(block), Some(Vector2::new(-1.0, 0.0)));
+        assert_eq!(ball.xy, Point2::new(88.0, 204.0));
+        assert_eq!(ball.delta, Vector2::new(-100.0, 20.0));
+        // Already on its way out.
+        ball.xy.x = 140.0;
+        ball.delta.x = 100.0;
+        assert_eq!(ball.bounce_off(block), None);
+        assert_eq!(ball.xy.x, 150.0);
+
+        ball.xy.y = -13.0;
+        ball.wrap(Side::Top);
+        assert_eq!(ball.xy.y, 720.0);
     }
 }
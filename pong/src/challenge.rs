@@ -0,0 +1,87 @@
+//! Modes played alone for a personal best: practice against a wall, for the
+//! longest rally, and time attacks, for the most points against the
+//! computer before the time is up.
+
+use serde::{Deserialize, Serialize};
+
+// Where the personal bests are kept, in the data directory.
+pub const BESTS: &str = "bests.json";
+
+/// Seconds of a time attack.
+pub const TIME_LIMIT: f32 = 90.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Challenge {
+    /// Alone, with a wall instead of the other paddle.
+    Practice,
+    /// Against the computer, until the time is up.
+    TimeAttack,
+}
+
+impl Challenge {
+    /// The challenge of a `--mode`, if it is one.
+    pub fn from_mode(mode: &str) -> Option<Challenge> {
+        match mode {
+            "practice" => Some(Challenge::Practice),
+            "time-attack" => Some(Challenge::TimeAttack),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bests {
+    /// Paddle hits of the longest practice rally.
+    pub rally: u32,
+    /// Points of the best time attack.
+    pub points: u32,
+}
+
+impl Bests {
+    pub fn get(&self, challenge: Challenge) -> u32 {
+        match challenge {
+            Challenge::Practice => self.rally,
+            Challenge::TimeAttack => self.points,
+        }
+    }
+
+    /// Keeps `score` if it beats the best of `challenge`, and tells whether
+    /// it did.
+    pub fn record(&mut self, challenge: Challenge, score: u32) -> bool {
+        let best = match challenge {
+            Challenge::Practice => &mut self.rally,
+            Challenge::TimeAttack => &mut self.points,
+        };
+        if score <= *best {
+            return false;
+        }
+        *best = score;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bests, Challenge};
+
+    #[test]
+    fn keeps_only_scores_beating_the_best() {
+        let mut bests = Bests::default();
+        assert!(bests.record(Challenge::Practice, 12));
+        assert!(!bests.record(Challenge::Practice, 12));
+        assert!(!bests.record(Challenge::Practice, 7));
+        assert!(bests.record(Challenge::TimeAttack, 3));
+        assert_eq!(bests.get(Challenge::Practice), 12);
+        assert_eq!(bests.get(Challenge::TimeAttack), 3);
+
+        // Files from before a challenge existed still load.
+        let old: Bests = serde_json::from_str(r#"{"rally": 4}"#).unwrap();
+        assert_eq!(old.points, 0);
+        assert_eq!(
+            Challenge::from_mode("time-attack"),
+            Some(Challenge::TimeAttack)
+        );
+        assert_eq!(Challenge::from_mode("2p"), None);
+    }
+}
@@ -1,341 +1,1822 @@
-use rand;
-use rand::Rng;
-use rand::rngs::ThreadRng;
-use std::path;
-
-use cgmath;
-use ggez::audio;
-use ggez::audio::SoundSource;
-use ggez::event::{KeyCode, KeyMods};
-use ggez::input::keyboard;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use cgmath::InnerSpace;
+use serde::Serialize;
+
+use ggez::input::keyboard::{KeyCode, KeyInput};
 use ggez::*;
 
+mod arena;
+use arena::Arena;
 mod paddle;
-use paddle::Paddle;
+use paddle::{Axis, Paddle};
 mod ball;
 use ball::Ball;
+mod challenge;
+use challenge::{Bests, Challenge, BESTS, TIME_LIMIT};
+mod side;
+use side::Side;
+mod powerup;
+use powerup::{Effect, Kind, PowerUp, Table};
+mod tournament;
+use tournament::{Setup, Tournament};
+mod stats;
+use engine::assets::{Assets, Fonts, Resources};
+use engine::audio::AudioManager;
+use engine::cli::{Flag, Options};
+use engine::console::{self, Command, Console};
+use engine::debug::{Info, Inspect, Overlay};
+use engine::juice::Juice;
+use engine::loading::{Launcher, Reload};
+use engine::logging;
+use engine::render::Renderer;
+use engine::save;
+use engine::settings::{OptionItem, Settings, OPTION_ITEMS};
+use engine::telemetry::Telemetry;
+use engine::text::{self, Align, TextCache};
+use engine::viewport::Screen;
+use stats::{Entry, Stats, HISTORY};
 
-const WIDTH: f32 = 1280.0;
-const HEIGHT: f32 = 720.0;
+const GAME_ID: &str = "pong";
+// The tournament in progress, in the data directory.
+const TOURNAMENT: &str = "tournament.json";
+
+const VIRTUAL_WIDTH: f32 = 1280.0;
+const VIRTUAL_HEIGHT: f32 = 720.0;
 const PADDLE_SPEED: f32 = 200.0;
+const PADDLE_LENGTH: f32 = 59.0;
+
+// Points to win a two-player match, and lives in a four-player one.
+const WINNING_SCORE: u32 = 10;
+const LIVES: u32 = 3;
+
+// With more than one ball allowed, another one comes in every few hits of a
+// rally, or after some time without one.
+const RALLY_STEP: u32 = 5;
+const SPAWN_TIME: f32 = 15.0;
 
-struct Fonts {
-    font: ggez::graphics::Font,
-    small: f32,
-    large: f32,
-    score: f32,
+const FLAGS: [Flag; 13] = [
+    Flag::Window,
+    Flag::Size,
+    Flag::Seed,
+    Flag::Mode(&["1p", "2p", "4p", "practice", "time-attack"]),
+    Flag::NoAudio,
+    // Play needs a serve first to get the ball moving.
+    Flag::State(&["start", "options", "serve"]),
+    Flag::Resources,
+    Flag::Log,
+    Flag::Telemetry,
+    Flag::Balls,
+    Flag::PowerUps,
+    Flag::History,
+    Flag::Arena,
+];
+
+// The options of every game. Pong has no music, so its volume is not
+// offered.
+fn option_items() -> Vec<OptionItem> {
+    let items = OPTION_ITEMS.iter().copied();
+    items
+        .filter(|&item| item != OptionItem::MusicVolume)
+        .collect()
 }
 
-struct Sounds {
-    paddle_hit: audio::Source,
-    wall_hit: audio::Source,
-    score: audio::Source,
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum SoundKind {
+    PaddleHit,
+    WallHit,
+    Score,
 }
 
+// The name of each sound in the asset manifest.
+const SOUNDS: [(SoundKind, &str); 3] = [
+    (SoundKind::PaddleHit, "paddle_hit"),
+    (SoundKind::WallHit, "wall_hit"),
+    (SoundKind::Score, "score"),
+];
+
+#[derive(Clone, Copy)]
 enum GameState {
     Start,
+    Options,
     Serve,
     Play,
     Done,
+    // Entering the players of a tournament.
+    Setup,
+    // The matches of the tournament so far.
+    Bracket,
+    // Over a match, which it goes back to.
+    Paused,
+}
+
+impl GameState {
+    fn name(&self) -> &'static str {
+        match self {
+            GameState::Start => "start",
+            GameState::Options => "options",
+            GameState::Serve => "serve",
+            GameState::Play => "play",
+            GameState::Done => "done",
+            GameState::Setup => "setup",
+            GameState::Bracket => "bracket",
+            GameState::Paused => "paused",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<GameState> {
+        match name {
+            "start" => Some(GameState::Start),
+            "options" => Some(GameState::Options),
+            "serve" => Some(GameState::Serve),
+            "play" => Some(GameState::Play),
+            "done" => Some(GameState::Done),
+            "setup" => Some(GameState::Setup),
+            "bracket" => Some(GameState::Bracket),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum PauseItem {
+    Resume,
+    Restart,
+    MainMenu,
+    Quit,
+}
+
+impl PauseItem {
+    fn label(self, tournament: bool) -> &'static str {
+        match self {
+            PauseItem::Resume => "Resume",
+            PauseItem::Restart => "Restart",
+            // A match of a tournament goes back to its bracket.
+            PauseItem::MainMenu if tournament => "Bracket",
+            PauseItem::MainMenu => "Main Menu",
+            PauseItem::Quit => "Quit",
+        }
+    }
+}
+
+const PAUSE_ITEMS: [PauseItem; 4] = [
+    PauseItem::Resume,
+    PauseItem::Restart,
+    PauseItem::MainMenu,
+    PauseItem::Quit,
+];
+
+// What the telemetry of a session records.
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event {
+    Session {
+        seed: u64,
+        mode: &'static str,
+    },
+    Serve {
+        player: u32,
+        dx: f32,
+        dy: f32,
+    },
+    Hit {
+        player: u32,
+        ball: usize,
+        x: f32,
+        y: f32,
+        speed: f32,
+    },
+    Spawn {
+        balls: usize,
+    },
+    PowerUp {
+        player: u32,
+        kind: &'static str,
+    },
+    Score {
+        player: u32,
+        player1: u32,
+        player2: u32,
+    },
+    Lost {
+        player: u32,
+        lives: u32,
+    },
+    Win {
+        player: u32,
+    },
+}
+
+const COMMANDS: [Command<State>; 6] = [
+    Command {
+        name: "lives",
+        usage: "lives <player> <lives>",
+        run: lives_command,
+    },
+    Command {
+        name: "set",
+        usage: "set ball.speed <pixels/s>",
+        run: set_command,
+    },
+    Command {
+        name: "score",
+        usage: "score <player> <points>",
+        run: score_command,
+    },
+    Command {
+        name: "spawn",
+        usage: "spawn multiball|powerup <kind>",
+        run: spawn_command,
+    },
+    Command {
+        name: "state",
+        usage: "state start|options|serve|play|done|setup|bracket",
+        run: state_command,
+    },
+    Command {
+        name: "timescale",
+        usage: "timescale <factor>",
+        run: timescale_command,
+    },
+];
+
+fn set_command(state: &mut State, args: &[&str]) -> Result<String, String> {
+    let usage = COMMANDS[1].usage;
+    if args.first() != Some(&"ball.speed") {
+        return Err(format!("usage: {}", usage));
+    }
+    let speed: f32 = console::arg(args, 1, usage)?;
+    let server = state.players[state.serving_player as usize - 1].side;
+    for ball in &mut state.balls {
+        let delta = &mut ball.delta;
+        let length = delta.x.hypot(delta.y);
+        if length == 0.0 {
+            // Not served yet, send it away from the serving player.
+            *delta = server.inward() * speed;
+        } else {
+            *delta *= speed / length;
+        }
+    }
+    Ok(format!("ball speed is {}", speed))
+}
+
+fn lives_command(state: &mut State, args: &[&str]) -> Result<String, String> {
+    let usage = COMMANDS[0].usage;
+    let player: usize = console::arg(args, 0, usage)?;
+    let lives = console::arg(args, 1, usage)?;
+    if !state.four {
+        return Err("only four-player matches have lives, see score".to_string());
+    }
+    let i = player.wrapping_sub(1);
+    if i >= state.players.len() {
+        return Err(format!("usage: {}", usage));
+    }
+    let others = (0..state.players.len()).filter(|&j| j != i && state.in_game(j));
+    if lives == 0 && others.count() == 0 {
+        return Err("someone has to stay in the game".to_string());
+    }
+    state.players[i].lives = lives;
+    // Serves don't come from a wall.
+    if lives == 0 && state.serving_player as usize == player {
+        state.new_server();
+    }
+    state.check_win();
+    Ok(format!("player {} has {} lives", player, lives))
+}
+
+fn score_command(state: &mut State, args: &[&str]) -> Result<String, String> {
+    let usage = COMMANDS[2].usage;
+    let player: usize = console::arg(args, 0, usage)?;
+    let points = console::arg(args, 1, usage)?;
+    if state.four {
+        return Err("four-player matches have lives, see lives".to_string());
+    }
+    match state.players.get_mut(player.wrapping_sub(1)) {
+        Some(defender) => defender.score = points,
+        None => return Err(format!("usage: {}", usage)),
+    }
+    state.check_win();
+    Ok(format!("player {} has {} points", player, points))
+}
+
+fn spawn_command(state: &mut State, args: &[&str]) -> Result<String, String> {
+    let usage = COMMANDS[3].usage;
+    if !matches!(state.state, GameState::Serve | GameState::Play) {
+        return Err("balls only come in during a match".to_string());
+    }
+    match args.first() {
+        Some(&"multiball") => {
+            // One more than --balls allows, if need be.
+            state.max_balls = state.max_balls.max(state.balls.len() + 1);
+            state.spawn();
+            Ok(format!("{} balls in play", state.balls.len()))
+        }
+        // In place of the one on the court, even without --power-ups.
+        Some(&"powerup") => match args.get(1).and_then(|name| Kind::from_name(name)) {
+            Some(kind) => {
+                state.power_up = Some(PowerUp::new(kind, &mut state.rng));
+                Ok(format!("a {} power-up is on the court", kind.name()))
+            }
+            None => {
+                let kinds: Vec<&str> = Kind::ALL.iter().map(|kind| kind.name()).collect();
+                Err(format!("kinds: {}", kinds.join(", ")))
+            }
+        },
+        _ => Err(format!("usage: {}", usage)),
+    }
+}
+
+fn state_command(state: &mut State, args: &[&str]) -> Result<String, String> {
+    let usage = COMMANDS[4].usage;
+    match args.first().and_then(|name| GameState::from_name(name)) {
+        // The end of a match shows who won it.
+        Some(GameState::Done) if state.winning_player == 0 => {
+            Err("nobody has won a match yet".to_string())
+        }
+        Some(game_state) => {
+            state.highlighted_option = 0;
+            state.change(game_state);
+            Ok(format!("state is {}", state.state.name()))
+        }
+        None => Err(format!("usage: {}", usage)),
+    }
+}
+
+fn timescale_command(state: &mut State, args: &[&str]) -> Result<String, String> {
+    let scale: f32 = console::arg(args, 0, COMMANDS[5].usage)?;
+    if scale < 0.0 {
+        return Err("the time scale can't be negative".to_string());
+    }
+    state.timescale = scale;
+    Ok(format!("time scale is {}", scale))
+}
+
+// Who defends a side of the court.
+struct Player {
+    side: Side,
+    paddle: Paddle,
+    // Points, in a two-player match.
+    score: u32,
+    // In a four-player match, the side becomes a wall once they are lost.
+    lives: u32,
+    computer: bool,
+    // The part of the paddle speed the computer plays at.
+    skill: f32,
+}
+
+impl Player {
+    fn new(side: Side, computer: bool) -> Player {
+        Player {
+            side,
+            paddle: side.paddle(),
+            score: 0,
+            lives: LIVES,
+            computer,
+            skill: 0.8,
+        }
+    }
+}
+
+// The players of a match in `mode`. In a four-player match, the computer
+// plays every side until its keys are pressed. In practice, a wall stands
+// where the other paddle would.
+fn players(mode: &str) -> Vec<Player> {
+    match mode {
+        "4p" => Side::ALL
+            .iter()
+            .map(|&side| Player::new(side, true))
+            .collect(),
+        "practice" => vec![Player::new(Side::Left, false)],
+        _ => vec![
+            Player::new(Side::Left, false),
+            Player::new(Side::Right, matches!(mode, "1p" | "time-attack")),
+        ],
+    }
 }
 
 struct State {
     state: GameState,
-    player1_score: u32,
-    player2_score: u32,
     winning_player: u32,
     serving_player: u32,
-    player1: Paddle,
-    player2: Paddle,
-    ball: Ball,
-    rng: ThreadRng,
+    // Left and right, then top and bottom in a four-player match.
+    players: Vec<Player>,
+    mode: &'static str,
+    four: bool,
+    // Never empty, and the one played in.
+    arenas: Vec<Arena>,
+    arena: usize,
+    // Never empty: the round ends when the last one goes out.
+    balls: Vec<Ball>,
+    max_balls: usize,
+    // Paddle hits since the serve.
+    rally: u32,
+    // Seconds since the serve or the last ball came in.
+    since_spawn: f32,
+    // With --power-ups, how often they come, the one waiting on the court,
+    // and the ones claimed.
+    power_ups: Option<Table>,
+    power_up: Option<PowerUp>,
+    effects: Vec<Effect>,
+    since_power_up: f32,
+    // The tournament set up or resumed, the entrants of the next one, and
+    // the round and index of the match being played.
+    tournament: Option<Tournament>,
+    setup: Setup,
+    playing: Option<(usize, usize)>,
+    // Of the match being played, and with --history, the longest rally and
+    // the fastest ball of every match before.
+    stats: Stats,
+    records: Option<(u32, f32)>,
+    // In practice or a time attack, the seconds left of the time attack,
+    // the personal bests, and whether the last time attack beat its own.
+    challenge: Option<Challenge>,
+    clock: f32,
+    bests: Bests,
+    new_best: bool,
+    rng: StdRng,
+    // Whether the computer plays both paddles, after the title screen was
+    // left idle.
+    demo: bool,
+    // Seconds without input on the title screen, or since the demo's last
+    // serve or match.
+    idle: f32,
     fonts: Fonts,
-    sounds: Sounds,
+    text_cache: TextCache,
+    audio: AudioManager<SoundKind>,
+    juice: Juice,
+    settings: Settings,
+    highlighted_option: usize,
+    // The state to resume once unpaused, and the item of the pause menu
+    // highlighted.
+    unpaused: GameState,
+    pause_item: usize,
+    screen: Screen,
+    overlay: Overlay,
+    console: Console,
+    // Speed of the game, changed from the console.
+    timescale: f32,
+    telemetry: Telemetry,
 }
 
 impl State {
-    fn new(ctx: &mut Context, rng: ThreadRng) -> GameResult<State> {
-        let player1 = Paddle::new(30.0, 90.0, 15.0, 59.0);
-        let player2 = Paddle::new(WIDTH - 30.0, HEIGHT - 90.0, 15.0, 59.0);
-        let ball = Ball::new();
-
-        let small = 24.0;
-        let font = ggez::graphics::Font::new(ctx, "/fonts/pong_font.ttf")?;
-
-        let sounds = Sounds {
-            paddle_hit: audio::Source::new(ctx, "/sounds/paddle_hit.wav")?,
-            wall_hit: audio::Source::new(ctx, "/sounds/wall_hit.wav")?,
-            score: audio::Source::new(ctx, "/sounds/score.wav")?,
+    fn new(
+        ctx: &mut Context,
+        seed: u64,
+        settings: Settings,
+        options: &Options,
+        assets: &Assets,
+    ) -> GameResult<State> {
+        let fonts = assets.fonts("pong", [24.0, 48.0, 72.0])?;
+        let text_cache = TextCache::new(&fonts);
+        let overlay = Overlay::new(&fonts);
+
+        let mut audio = AudioManager::new();
+        audio.apply_settings(&settings);
+        for &(kind, name) in SOUNDS.iter() {
+            audio.add(ctx, kind, name, assets.sound(name)?);
+        }
+        if options.no_audio {
+            audio.mute();
+        }
+        let mut juice = Juice::new();
+        juice.apply_settings(&settings);
+        let state = options.state.as_deref().and_then(GameState::from_name);
+        let mode = match options.mode.as_deref() {
+            Some("1p") => "1p",
+            Some("4p") => "4p",
+            Some("practice") => "practice",
+            Some("time-attack") => "time-attack",
+            _ => "2p",
+        };
+        let four = mode == "4p";
+        let challenge = Challenge::from_mode(mode);
+        let bests = match challenge {
+            Some(_) => save::load(GAME_ID, BESTS).unwrap_or_default(),
+            None => Bests::default(),
+        };
+        let records = if options.history {
+            let entries: Vec<Entry> = save::load_lines(GAME_ID, HISTORY);
+            log::info!("{} matches in the history", entries.len());
+            Some(stats::records(entries.iter().map(|entry| &entry.stats)))
+        } else {
+            None
+        };
+        let arenas = arenas(assets);
+        let arena = match &options.arena {
+            Some(name) => arenas.iter().position(|arena| &arena.name == name),
+            None => Some(0),
+        };
+        if arena.is_none() {
+            log::warn!(
+                "no arena {:?}, playing in {}",
+                options.arena,
+                arenas[0].name
+            );
+        }
+        let power_ups = if options.power_ups {
+            power_up_table(assets)
+        } else {
+            None
         };
+        let mut telemetry = Telemetry::disabled();
+        if options.telemetry {
+            telemetry = Telemetry::start(GAME_ID);
+            telemetry.record(&Event::Session { seed, mode });
+        }
+
+        let screen = Screen::new(ctx, VIRTUAL_WIDTH, VIRTUAL_HEIGHT, false)?;
 
+        let players = players(mode);
         let state = State {
-            state: GameState::Start,
-            player1_score: 0,
-            player2_score: 0,
+            state: state.unwrap_or(GameState::Start),
             winning_player: 0,
             serving_player: 1,
-            player1,
-            player2,
-            ball,
-            rng,
-            fonts: Fonts {
-                font,
-                small,
-                large: 48.0,
-                score: 72.0,
-            },
-            sounds,
+            stats: Stats::new(players.len()),
+            records,
+            challenge,
+            clock: TIME_LIMIT,
+            bests,
+            new_best: false,
+            players,
+            mode,
+            four,
+            arenas,
+            arena: arena.unwrap_or(0),
+            balls: vec![Ball::new()],
+            max_balls: options.balls.unwrap_or(1) as usize,
+            rally: 0,
+            since_spawn: 0.0,
+            power_ups,
+            power_up: None,
+            effects: Vec::new(),
+            since_power_up: 0.0,
+            tournament: None,
+            setup: Setup::new(),
+            playing: None,
+            rng: StdRng::seed_from_u64(seed),
+            demo: false,
+            idle: 0.0,
+            fonts,
+            text_cache,
+            audio,
+            juice,
+            settings,
+            highlighted_option: 0,
+            unpaused: GameState::Serve,
+            pause_item: 0,
+            screen,
+            overlay,
+            console: Console::new(),
+            timescale: 1.0,
+            telemetry,
         };
         Ok(state)
     }
 
-    fn display_score(&mut self, ctx: &mut Context) -> GameResult<()> {
-        let score1 = format!("{}", self.player1_score);
-        let text1 = ggez::graphics::Text::new((score1, self.fonts.font, self.fonts.score));
-        let pos1 = cgmath::Point2::new(WIDTH / 2.0 - 148.0, HEIGHT / 3.0);
-        ggez::graphics::draw(ctx, &text1, (pos1,))?;
+    fn change(&mut self, state: GameState) {
+        log::info!("entering state {}", state.name());
+        if let GameState::Serve = state {
+            self.rally = 0;
+            self.since_spawn = 0.0;
+        }
+        self.state = state;
+        self.idle = 0.0;
+    }
 
-        let score2 = format!("{}", self.player2_score);
-        let text2 = ggez::graphics::Text::new((score2, self.fonts.font, self.fonts.score));
-        let pos2 = cgmath::Point2::new(WIDTH / 2.0 + 89.0, HEIGHT / 3.0);
-        ggez::graphics::draw(ctx, &text2, (pos2,))
+    // The demo is not recorded.
+    fn record(&mut self, event: Event) {
+        if !self.demo {
+            self.telemetry.record(&event);
+        }
     }
 
-    fn display_fps(&mut self, ctx: &mut Context) -> GameResult<()> {
-        let color = ggez::graphics::Color::new(0.0, 1.0, 0.0, 1.0);
-        let fps = format!("FPS: {:.2}", timer::fps(ctx));
-        let text = ggez::graphics::Text::new((fps, self.fonts.font, self.fonts.small));
-        let pos = cgmath::Point2::new(30.0, 30.0);
-        ggez::graphics::draw(ctx, &text, (pos, color))
+    // A paddle sent ball `b` back.
+    fn hit(&mut self, player: u32, b: usize) {
+        let ball = &self.balls[b];
+        let speed = ball.delta.magnitude();
+        self.record(Event::Hit {
+            player,
+            ball: b,
+            x: ball.xy.x,
+            y: ball.xy.y,
+            speed,
+        });
+        self.rally += 1;
+        self.stats.hit(player as usize - 1, self.rally, speed);
+        if self.rally.is_multiple_of(RALLY_STEP) {
+            self.spawn();
+        }
     }
-}
 
-fn center(text_len: f32, font_size: f32) -> f32 {
-    let middle = WIDTH / 2.0;
-    let total_len = text_len * font_size;
-    middle - (total_len / 4.0)
-}
+    // Away from `side`, like a serve.
+    fn launch(&mut self, side: Side) -> cgmath::Vector2<f32> {
+        let across = self.rng.gen_range(-50.0..50.0);
+        let along = self.rng.gen_range(140.0..200.0);
+        side.inward() * along + side.across() * across
+    }
 
-impl ggez::event::EventHandler for State {
-    fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
-        let dt = timer::delta(ctx).as_secs_f32();
-        // println!("dt {}", dt);
+    // Another ball in the middle, away from a random defended side.
+    fn spawn(&mut self) {
+        self.since_spawn = 0.0;
+        if self.balls.len() >= self.max_balls {
+            return;
+        }
+        let sides: Vec<Side> = (0..self.players.len())
+            .filter(|&i| self.in_game(i))
+            .map(|i| self.players[i].side)
+            .collect();
+        if sides.is_empty() {
+            return;
+        }
+        let side = sides[self.rng.gen_range(0..sides.len())];
+        let mut ball = Ball::new();
+        ball.delta = self.launch(side);
+        self.balls.push(ball);
+        let balls = self.balls.len();
+        self.record(Event::Spawn { balls });
+    }
 
-        match self.state {
-            GameState::Serve => {
-                self.ball.delta.y = self.rng.gen_range(-50.0..50.0);
-                if self.serving_player == 1 {
-                    self.ball.delta.x = self.rng.gen_range(140.0..200.0);
-                } else {
-                    self.ball.delta.x = -self.rng.gen_range(140.0..200.0);
-                }
+    // Bounces ball `b` off what it hit, and gives the player it went past.
+    fn collide(&mut self, ctx: &Context, b: usize) -> Option<usize> {
+        for i in 0..self.players.len() {
+            let (defending, curving) = (self.in_game(i), self.has(i, Kind::Curve));
+            let (ball, player) = (&mut self.balls[b], &self.players[i]);
+            if !defending || !ball.collides(&player.paddle) {
+                continue;
             }
-            GameState::Play => {
-                if self.ball.collides(&self.player1) {
-                    self.ball.delta.x = -self.ball.delta.x * 1.03;
-                    self.ball.xy.x = self.player1.xy.x + 15.0;
+            ball.deflect(player.side, &player.paddle);
+            ball.last_hit = Some(i);
+            self.juice.shake(ball.delta.magnitude() / 2.0);
+            self.juice.flash(i);
+            self.juice.squash(b, player.side.inward());
+            let across: f32 = self.rng.gen_range(10.0..150.0);
+            let delta = &mut ball.delta;
+            match player.side.axis() {
+                Axis::Vertical => delta.y = across.copysign(delta.y),
+                Axis::Horizontal => delta.x = across.copysign(delta.x),
+            }
+            ball.curve = match curving {
+                true if self.rng.gen_bool(0.5) => 1.2,
+                true => -1.2,
+                false => 0.0,
+            };
+            self.audio.play(ctx, SoundKind::PaddleHit);
+            self.hit(i as u32 + 1, b);
+        }
 
-                    if self.ball.delta.y < 0.0 {
-                        self.ball.delta.y = -self.rng.gen_range(10.0..150.0)
-                    } else {
-                        self.ball.delta.y = self.rng.gen_range(10.0..150.0)
+        for obstacle in self.arenas[self.arena].obstacles() {
+            self.bounce(ctx, b, obstacle);
+        }
+        for &side in Side::ALL.iter() {
+            // Sides nobody defends are walls, and so is the back wall
+            // around a narrow goal.
+            let defender =
+                (0..self.players.len()).find(|&i| self.players[i].side == side && self.in_game(i));
+            let arena = &self.arenas[self.arena];
+            match defender {
+                Some(i) if self.has(i, Kind::Shield) => self.bounce(ctx, b, side.boundary()),
+                Some(i) => {
+                    for post in arena.posts(side, side.boundary()) {
+                        self.bounce(ctx, b, post);
+                    }
+                    if self.balls[b].out(side) {
+                        return Some(i);
                     }
-                    self.sounds.paddle_hit.play(ctx)?
                 }
+                None if arena.wrap && side.axis() == Axis::Horizontal => self.balls[b].wrap(side),
+                None => self.bounce(ctx, b, side.boundary()),
+            }
+        }
+        None
+    }
 
-                if self.ball.collides(&self.player2) {
-                    self.ball.delta.x = -self.ball.delta.x * 1.03;
-                    self.ball.xy.x = self.player2.xy.x - 12.0;
+    fn bounce(&mut self, ctx: &Context, b: usize, rect: graphics::Rect) {
+        if let Some(normal) = self.balls[b].bounce_off(rect) {
+            self.audio.play(ctx, SoundKind::WallHit);
+            self.juice.squash(b, normal);
+        }
+    }
 
-                    if self.ball.delta.y < 0.0 {
-                        self.ball.delta.y = -self.rng.gen_range(10.0..150.0)
-                    } else {
-                        self.ball.delta.y = self.rng.gen_range(10.0..150.0)
-                    }
-                    self.sounds.paddle_hit.play(ctx)?
-                }
+    // Whether player `i` holds a power-up of this kind.
+    fn has(&self, i: usize, kind: Kind) -> bool {
+        let held = |effect: &Effect| effect.owner == i && effect.kind == kind;
+        self.effects.iter().any(held)
+    }
 
-                if self.ball.xy.y <= 0.0 {
-                    self.ball.xy.y = 0.0;
-                    self.ball.delta.y = -self.ball.delta.y;
-                    self.sounds.wall_hit.play(ctx)?
-                }
+    // Wears the power-ups off, brings new ones in, and hands the one on the
+    // court to whoever last hit a ball through it.
+    fn update_power_ups(&mut self, ctx: &Context, dt: f32) {
+        for effect in &mut self.effects {
+            effect.left -= dt;
+        }
+        self.effects.retain(|effect| effect.left > 0.0);
 
-                if self.ball.xy.y >= HEIGHT - 12.0 {
-                    // BALL SIZE
-                    self.ball.xy.y = HEIGHT - 12.0;
-                    self.ball.delta.y = -self.ball.delta.y;
-                    self.sounds.wall_hit.play(ctx)?
-                }
+        if self.power_up.is_none() {
+            // Only the console brings them in without --power-ups.
+            let table = match &self.power_ups {
+                Some(table) => table,
+                None => return,
+            };
+            self.since_power_up += dt;
+            if self.since_power_up >= table.interval {
+                self.since_power_up = 0.0;
+                let kind = table.pick(&mut self.rng);
+                self.power_up = kind.map(|kind| PowerUp::new(kind, &mut self.rng));
+            }
+            return;
+        }
+        let (kind, rect) = match &self.power_up {
+            Some(power_up) => (power_up.kind, power_up.rect()),
+            None => return,
+        };
+        let claimed = self.balls.iter().enumerate().find_map(|(b, ball)| {
+            let through = rect.overlaps(&ball.hitbox());
+            ball.last_hit.filter(|_| through).map(|i| (i, b))
+        });
+        if let Some((i, b)) = claimed {
+            self.power_up = None;
+            self.claim(ctx, kind, i, b);
+        }
+    }
 
-                if self.ball.xy.x < 0.0 {
-                    self.serving_player = 1;
-                    self.player2_score = self.player2_score + 1;
-                    self.sounds.score.play(ctx)?;
+    // Player `i` gets a power-up, by hitting ball `b` through it.
+    fn claim(&mut self, ctx: &Context, kind: Kind, i: usize, b: usize) {
+        log::info!("player {} claimed {}", i + 1, kind.name());
+        self.record(Event::PowerUp {
+            player: i as u32 + 1,
+            kind: kind.name(),
+        });
+        self.audio.play(ctx, SoundKind::WallHit);
+        if let Kind::Split = kind {
+            // Mirrored across their side, so that the two part ways.
+            let across = self.players[i].side.across();
+            let mut ball = self.balls[b].clone();
+            ball.delta -= across * 2.0 * ball.delta.dot(across);
+            return self.balls.push(ball);
+        }
+        // Claiming one again starts it over.
+        let left = kind.duration();
+        match self
+            .effects
+            .iter_mut()
+            .find(|e| e.owner == i && e.kind == kind)
+        {
+            Some(effect) => effect.left = left,
+            None => self.effects.push(Effect {
+                kind,
+                owner: i,
+                left,
+            }),
+        }
+    }
 
-                    if self.player2_score == 10 {
-                        self.winning_player = 2;
-                        self.state = GameState::Done
-                    } else {
-                        self.state = GameState::Serve;
-                        self.ball.reset()
-                    }
-                }
+    // Whether player `i` still defends their side.
+    fn in_game(&self, i: usize) -> bool {
+        !self.four || self.players[i].lives > 0
+    }
+
+    // A ball went past the paddle of player `i`: the other player scores,
+    // or they lose a life in a four-player match. Whoever conceded serves.
+    fn concede(&mut self, ctx: &Context, i: usize) {
+        self.audio.play(ctx, SoundKind::Score);
+        self.stats.concede(i, self.serving_player as usize - 1);
+        if let Some(Challenge::Practice) = self.challenge {
+            return self.end_rally();
+        }
+        let (popup, color, player) = if self.four {
+            ("-1", graphics::Color::new(1.0, 0.4, 0.4, 1.0), i)
+        } else {
+            ("+1", graphics::Color::new(1.0, 1.0, 0.0, 1.0), 1 - i)
+        };
+        let (pos, _) = self.score_position(player);
+        self.juice.popup(popup, pos, color);
+        self.serving_player = i as u32 + 1;
+        if self.four {
+            let lives = self.players[i].lives - 1;
+            self.players[i].lives = lives;
+            self.record(Event::Lost {
+                player: i as u32 + 1,
+                lives,
+            });
+            if let Some(winner) = self.winner() {
+                return self.win(winner);
+            }
+            if lives == 0 {
+                log::info!("player {} is out", i + 1);
+                self.new_server();
+            }
+        } else {
+            let other = 1 - i;
+            self.players[other].score += 1;
+            self.record(Event::Score {
+                player: other as u32 + 1,
+                player1: self.players[0].score,
+                player2: self.players[1].score,
+            });
+            if let Some(winner) = self.winner() {
+                self.win(winner);
+            }
+        }
+    }
 
-                if self.ball.xy.x > WIDTH {
-                    self.serving_player = 2;
-                    self.player1_score = self.player1_score + 1;
-                    self.sounds.score.play(ctx)?;
+    // Who won the match, if anyone did yet: the last player left in a
+    // four-player match, or the first to the winning score. A time attack
+    // goes on until the time is up.
+    fn winner(&self) -> Option<usize> {
+        if self.four {
+            let mut left = (0..self.players.len()).filter(|&i| self.in_game(i));
+            return match (left.next(), left.next()) {
+                (Some(winner), None) => Some(winner),
+                _ => None,
+            };
+        }
+        if self.challenge.is_some() {
+            return None;
+        }
+        (0..self.players.len()).find(|&i| self.players[i].score >= WINNING_SCORE)
+    }
 
-                    if self.player1_score == 10 {
-                        self.winning_player = 1;
-                        self.state = GameState::Done
-                    } else {
-                        self.state = GameState::Serve;
-                        self.ball.reset()
-                    }
+    // Ends the match if the console just won it.
+    fn check_win(&mut self) {
+        let playing = match self.state {
+            GameState::Paused => self.unpaused,
+            state => state,
+        };
+        if let (GameState::Serve | GameState::Play, Some(winner)) = (playing, self.winner()) {
+            self.win(winner);
+        }
+    }
+
+    // One of the players still in the game, at random, serves next.
+    fn new_server(&mut self) {
+        let left: Vec<usize> = (0..self.players.len())
+            .filter(|&j| self.in_game(j))
+            .collect();
+        self.serving_player = left[self.rng.gen_range(0..left.len())] as u32 + 1;
+    }
+
+    fn win(&mut self, i: usize) {
+        self.winning_player = i as u32 + 1;
+        self.record(Event::Win {
+            player: self.winning_player,
+        });
+        if let (Some((rally, speed)), false) = (self.records, self.demo) {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH);
+            let entry = Entry {
+                end: now.map_or(0, |now| now.as_secs()),
+                mode: self.mode.to_string(),
+                winner: self.player_name(i),
+                scores: self
+                    .players
+                    .iter()
+                    .map(|player| {
+                        if self.four {
+                            player.lives
+                        } else {
+                            player.score
+                        }
+                    })
+                    .collect(),
+                stats: self.stats.clone(),
+            };
+            save::append(GAME_ID, HISTORY, &entry);
+            let records = (
+                rally.max(entry.stats.longest_rally),
+                speed.max(entry.stats.top_speed),
+            );
+            self.records = Some(records);
+        }
+        self.change(GameState::Done)
+    }
+
+    // The rally of a practice is over, and may be the longest yet.
+    fn end_rally(&mut self) {
+        if self.demo || !self.bests.record(Challenge::Practice, self.rally) {
+            return;
+        }
+        save::store(GAME_ID, BESTS, &self.bests);
+        let (mut pos, size) = self.score_position(0);
+        pos.y -= size;
+        self.juice
+            .popup("New best!", pos, graphics::Color::new(1.0, 1.0, 0.0, 1.0));
+    }
+
+    // The time attack is over, with the points the player scored.
+    fn time_up(&mut self) {
+        self.clock = 0.0;
+        let points = self.players[0].score;
+        self.new_best = !self.demo && self.bests.record(Challenge::TimeAttack, points);
+        if self.new_best {
+            save::store(GAME_ID, BESTS, &self.bests);
+        }
+        self.winning_player = 1;
+        self.change(GameState::Done)
+    }
+
+    fn new_match(&mut self) {
+        for player in &mut self.players {
+            player.score = 0;
+            player.lives = LIVES;
+        }
+        self.clock = TIME_LIMIT;
+        self.new_best = false;
+        self.serving_player = 1;
+        self.juice.clear();
+        self.stats = Stats::new(self.players.len());
+        self.balls = vec![Ball::new()];
+        self.power_up = None;
+        self.effects.clear();
+        self.since_power_up = 0.0;
+    }
+
+    // Whether a match is on, from its first serve to its end.
+    fn in_match(&self) -> bool {
+        matches!(
+            self.state,
+            GameState::Serve | GameState::Play | GameState::Done
+        )
+    }
+
+    // A match can be paused while it is played, but not the demo.
+    fn pause(&mut self) {
+        if self.demo || !self.in_match() {
+            return;
+        }
+        self.unpaused = self.state;
+        self.pause_item = 0;
+        self.change(GameState::Paused)
+    }
+
+    fn choose(&mut self, ctx: &mut Context, item: PauseItem) {
+        match item {
+            PauseItem::Resume => self.change(self.unpaused),
+            PauseItem::Restart => {
+                self.new_match();
+                self.change(GameState::Serve)
+            }
+            // The match of a tournament is not counted.
+            PauseItem::MainMenu if self.playing.is_some() => {
+                self.playing = None;
+                self.change(GameState::Bracket)
+            }
+            PauseItem::MainMenu => {
+                self.new_match();
+                self.change(GameState::Start)
+            }
+            PauseItem::Quit => ctx.request_quit(),
+        }
+    }
+
+    // Resumes the saved tournament, if any, or sets up a new one.
+    fn open_tournament(&mut self) {
+        if self.tournament.is_none() {
+            self.tournament = save::load(GAME_ID, TOURNAMENT);
+        }
+        match self.tournament {
+            Some(_) => self.change(GameState::Bracket),
+            None => self.change(GameState::Setup),
+        }
+    }
+
+    // Plays the next match of the tournament, or ends it once it has its
+    // champion.
+    fn next_match(&mut self) {
+        let tournament = match &self.tournament {
+            Some(tournament) => tournament,
+            None => return self.change(GameState::Setup),
+        };
+        let (round, index) = match tournament.next() {
+            Some(next) => next,
+            None => {
+                save::remove(GAME_ID, TOURNAMENT);
+                self.tournament = None;
+                return self.leave_tournament();
+            }
+        };
+        let entrants = tournament.players(round, index);
+        let sides = [Side::Left, Side::Right];
+        self.players = (0..2)
+            .map(|i| {
+                let mut player = Player::new(sides[i], entrants[i].computer.is_some());
+                if let Some(difficulty) = entrants[i].computer {
+                    player.skill = difficulty.skill();
                 }
+                player
+            })
+            .collect();
+        log::info!("playing {} vs {}", entrants[0].name, entrants[1].name);
+        self.playing = Some((round, index));
+        self.new_match();
+        self.change(GameState::Serve)
+    }
+
+    // Records the result of the match just played, and shows the bracket.
+    fn report(&mut self) {
+        if let (Some((round, index)), Some(tournament)) = (self.playing, &mut self.tournament) {
+            let score = [self.players[0].score, self.players[1].score];
+            tournament.report(round, index, score);
+            save::store(GAME_ID, TOURNAMENT, tournament);
+        }
+        self.playing = None;
+        self.change(GameState::Bracket)
+    }
+
+    // Back to the title, with the players of the mode the game started in.
+    // The tournament stays saved.
+    fn leave_tournament(&mut self) {
+        self.playing = None;
+        self.players = players(self.mode);
+        self.new_match();
+        self.change(GameState::Start)
+    }
+
+    // Their name in the tournament, or their number.
+    fn player_name(&self, i: usize) -> String {
+        match (self.playing, &self.tournament) {
+            (Some((round, index)), Some(tournament)) => {
+                tournament.players(round, index)[i].name.clone()
+            }
+            _ => format!("Player {}", i + 1),
+        }
+    }
+
+    // Serves and restarts matches on its own, for the computer to play.
+    fn attract(&mut self, dt: f32) {
+        self.idle += dt;
+        let delay = self.settings.attract_delay;
+        match self.state {
+            GameState::Start if delay > 0.0 && self.idle >= delay => {
+                log::info!("starting the demo");
+                self.demo = true;
+                self.new_match();
+                self.change(GameState::Serve)
+            }
+            GameState::Serve if self.demo && self.idle >= 1.0 => self.change(GameState::Play),
+            GameState::Done if self.demo && self.idle >= 3.0 => {
+                self.new_match();
+                self.change(GameState::Serve)
             }
             _ => {}
         }
+    }
 
-        if keyboard::is_key_pressed(ctx, KeyCode::W) {
-            self.player1.dy = -PADDLE_SPEED
-        } else if keyboard::is_key_pressed(ctx, KeyCode::S) {
-            self.player1.dy = PADDLE_SPEED
-        } else {
-            self.player1.dy = 0.0
+    // Any input ends the demo, and is not used otherwise.
+    fn wake(&mut self) -> bool {
+        self.idle = 0.0;
+        if !self.demo {
+            return false;
         }
+        self.demo = false;
+        self.new_match();
+        self.change(GameState::Start);
+        true
+    }
 
-        if keyboard::is_key_pressed(ctx, KeyCode::Up) {
-            self.player2.dy = -PADDLE_SPEED
-        } else if keyboard::is_key_pressed(ctx, KeyCode::Down) {
-            self.player2.dy = PADDLE_SPEED
+    // Speed of a computer paddle: it goes after the ball once it comes its
+    // way, a little slower than a player could.
+    fn follow_ball(&self, i: usize) -> f32 {
+        let Player {
+            side,
+            paddle,
+            skill,
+            ..
+        } = &self.players[i];
+        let inward = side.inward();
+        // The closest of the balls coming its way.
+        let distance = |ball: &&Ball| ball.xy.x * inward.x + ball.xy.y * inward.y;
+        let ball = self
+            .balls
+            .iter()
+            .filter(|ball| ball.delta.dot(inward) < 0.0)
+            .min_by(|a, b| distance(a).total_cmp(&distance(b)));
+        let ball = match ball {
+            Some(ball) if matches!(self.state, GameState::Play) => ball,
+            _ => return 0.0,
+        };
+        let (paddle, ball) = match paddle.axis {
+            Axis::Vertical => (paddle.xy.y + paddle.size.y / 2.0, ball.xy.y + 6.0),
+            Axis::Horizontal => (paddle.xy.x + paddle.size.x / 2.0, ball.xy.x + 6.0),
+        };
+        if ball < paddle - 10.0 {
+            -PADDLE_SPEED * skill
+        } else if ball > paddle + 10.0 {
+            PADDLE_SPEED * skill
         } else {
-            self.player2.dy = 0.0
+            0.0
+        }
+    }
+
+    // Where the score or lives of player `i` are shown, and their size.
+    fn score_position(&self, i: usize) -> (cgmath::Point2<f32>, f32) {
+        let (w, h) = (VIRTUAL_WIDTH, VIRTUAL_HEIGHT);
+        if !self.four {
+            let x = if i == 0 {
+                w / 2.0 - 148.0
+            } else {
+                w / 2.0 + 89.0
+            };
+            return (cgmath::Point2::new(x, h / 3.0), self.fonts.large);
+        }
+        let size = self.fonts.small;
+        let pos = match self.players[i].side {
+            Side::Left => cgmath::Point2::new(60.0, h / 2.0 - size / 2.0),
+            Side::Right => cgmath::Point2::new(w - 160.0, h / 2.0 - size / 2.0),
+            Side::Top => cgmath::Point2::new(w / 2.0 - 50.0, 60.0),
+            Side::Bottom => cgmath::Point2::new(w / 2.0 - 50.0, h - 60.0 - size),
+        };
+        (pos, size)
+    }
+
+    fn display_score(&mut self, renderer: &mut dyn Renderer) {
+        if self.four {
+            return self.display_lives(renderer);
+        }
+        if let Some(challenge) = self.challenge {
+            return self.display_challenge(renderer, challenge);
+        }
+        let size = self.fonts.large;
+        let ((pos1, _), (pos2, _)) = (self.score_position(0), self.score_position(1));
+        let score1 = format_args!("{}", self.players[0].score);
+        let text1 = self.text_cache.get("player1_score", score1, size);
+        renderer.text(text1, pos1, graphics::Color::WHITE);
+
+        let score2 = format_args!("{}", self.players[1].score);
+        let text2 = self.text_cache.get("player2_score", score2, size);
+        renderer.text(text2, pos2, graphics::Color::WHITE)
+    }
+
+    // The rally of a practice, or the time left of a time attack, in the
+    // middle, with the personal best under it.
+    fn display_challenge(&mut self, renderer: &mut dyn Renderer, challenge: Challenge) {
+        let (large, small) = (self.fonts.large, self.fonts.small);
+        let (best, points) = (self.bests.get(challenge), self.players[0].score);
+        let top = VIRTUAL_HEIGHT / 3.0;
+        let white = graphics::Color::WHITE;
+        let gray = graphics::Color::new(0.7, 0.7, 0.7, 1.0);
+        let seconds = self.clock.ceil() as u32;
+        let cache = &mut self.text_cache;
+        let counter = match challenge {
+            Challenge::Practice => cache.get("challenge", format_args!("{}", self.rally), large),
+            Challenge::TimeAttack => {
+                let left = format_args!("{}:{:02}", seconds / 60, seconds % 60);
+                cache.get("challenge", left, large)
+            }
+        };
+        text::draw_aligned(renderer, counter, line(top), Align::Center, white);
+        let under = match challenge {
+            Challenge::Practice => format_args!("Rally, best {}", best),
+            Challenge::TimeAttack => format_args!("{} points, best {}", points, best),
+        };
+        let under = cache.get("best", under, small);
+        text::draw_aligned(
+            renderer,
+            under,
+            line(top + large + 4.0),
+            Align::Center,
+            gray,
+        );
+    }
+
+    // The icons of the power-ups each player holds, under their score.
+    fn display_effects(&self, renderer: &mut dyn Renderer) {
+        let size = self.fonts.small;
+        for i in 0..self.players.len() {
+            let (mut pos, height) = self.score_position(i);
+            pos.y += height + 8.0;
+            for effect in self.effects.iter().filter(|effect| effect.owner == i) {
+                let text = self.fonts.text(effect.kind.icon(), size);
+                renderer.text(&text, pos, effect.kind.color());
+                pos.x += size;
+            }
+        }
+    }
+
+    // Next to each wall, who defends it and their lives left.
+    fn display_lives(&mut self, renderer: &mut dyn Renderer) {
+        let size = self.fonts.small;
+        let positions: Vec<_> = (0..self.players.len())
+            .map(|i| self.score_position(i).0)
+            .collect();
+        let cache = &mut self.text_cache;
+        for (i, player) in self.players.iter().enumerate() {
+            let who = if player.computer { "CPU" } else { "P" };
+            let slot = player.side.name();
+            let text = match player.lives {
+                0 => cache.get(slot, format_args!("{}{} out", who, i + 1), size),
+                lives => cache.get(slot, format_args!("{}{} {}", who, i + 1, lives), size),
+            };
+            renderer.text(text, positions[i], graphics::Color::WHITE);
+        }
+    }
+}
+
+// The arenas of the data, or only the classic one if they can't be read.
+fn arenas(assets: &Assets) -> Vec<Arena> {
+    let arenas = match assets.level("arenas") {
+        Ok(content) => arena::parse(content).map_err(|e| format!("arenas: {}", e)),
+        Err(e) => Err(e.to_string()),
+    };
+    arenas.unwrap_or_else(|e| {
+        log::warn!("only the classic arena, {}", e);
+        vec![Arena::default()]
+    })
+}
+
+// How often power-ups come, or none if their table is missing or wrong.
+fn power_up_table(assets: &Assets) -> Option<Table> {
+    let table = match assets.level("powerups") {
+        Ok(content) => Table::parse(content).map_err(|e| format!("powerups: {}", e)),
+        Err(e) => Err(e.to_string()),
+    };
+    table.map_err(|e| log::warn!("no power-ups, {}", e)).ok()
+}
+
+// A full-width line of the court, to center text on.
+fn line(y: f32) -> graphics::Rect {
+    graphics::Rect::new(0.0, y, VIRTUAL_WIDTH, 0.0)
+}
+
+impl Reload for State {
+    fn reload(&mut self, ctx: &mut Context, assets: &Assets) -> GameResult<()> {
+        for &(kind, name) in SOUNDS.iter() {
+            if let Ok(data) = assets.sound(name) {
+                self.audio.add(ctx, kind, name, data);
+            }
+        }
+        // The same arena, if it is still there.
+        let name = self.arenas[self.arena].name.clone();
+        self.arenas = arenas(assets);
+        self.arena = self
+            .arenas
+            .iter()
+            .position(|arena| arena.name == name)
+            .unwrap_or(0);
+        if self.power_ups.is_some() {
+            if let Some(table) = power_up_table(assets) {
+                self.power_ups = Some(table);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ggez::event::EventHandler for State {
+    fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let start = Instant::now();
+        let dt = ctx.time.delta().as_secs_f32() * self.timescale;
+        // println!("dt {}", dt);
+        if let GameState::Paused = self.state {
+            self.overlay.record_update(start.elapsed());
+            return Ok(());
+        }
+        self.attract(dt);
+        if let GameState::Serve | GameState::Play = self.state {
+            self.stats.time += dt;
+            if let Some(Challenge::TimeAttack) = self.challenge {
+                self.clock -= dt;
+                if self.clock <= 0.0 {
+                    self.time_up();
+                }
+            }
         }
 
         match self.state {
-            GameState::Play => self.ball.update(dt),
-            _ => (),
+            GameState::Serve => {
+                // Away from the serving player.
+                let side = self.players[self.serving_player as usize - 1].side;
+                self.balls[0].delta = self.launch(side);
+            }
+            GameState::Play => {
+                let mut b = 0;
+                while b < self.balls.len() {
+                    let conceded = match self.collide(ctx, b) {
+                        Some(i) => i,
+                        None => {
+                            b += 1;
+                            continue;
+                        }
+                    };
+                    self.juice.shake(self.balls[b].delta.magnitude());
+                    // A point per ball, the round goes on while any is left.
+                    let last = self.balls.len() == 1;
+                    if last {
+                        self.balls[0].reset();
+                        self.juice.clear();
+                    } else {
+                        let _ = self.balls.remove(b);
+                    }
+                    // Before the serve starts the next rally from nothing.
+                    self.concede(ctx, conceded);
+                    if let GameState::Done = self.state {
+                        break;
+                    }
+                    if last {
+                        self.change(GameState::Serve);
+                    }
+                }
+                self.since_spawn += dt;
+                if self.since_spawn >= SPAWN_TIME {
+                    self.spawn();
+                }
+                self.update_power_ups(ctx, dt);
+            }
+            _ => {}
+        }
+
+        // Keys typed in the console don't move the paddles.
+        let typing = self.console.open;
+        let pressed = |key| !typing && ctx.keyboard.is_key_pressed(key);
+        for i in 0..self.players.len() {
+            let [back, forth] = self.players[i].side.keys();
+            if self.four && (pressed(back) || pressed(forth)) {
+                self.players[i].computer = false;
+            }
+            let mut length = PADDLE_LENGTH;
+            if self.has(i, Kind::Grow) {
+                length *= 1.5;
+            }
+            if self
+                .effects
+                .iter()
+                .any(|e| e.kind == Kind::Shrink && e.owner != i)
+            {
+                length *= 0.6;
+            }
+            self.players[i].paddle.set_length(length);
+            let speed = if self.demo || self.players[i].computer {
+                self.follow_ball(i)
+            } else if pressed(back) {
+                -PADDLE_SPEED
+            } else if pressed(forth) {
+                PADDLE_SPEED
+            } else {
+                0.0
+            };
+            let boost = if self.has(i, Kind::Fast) { 1.5 } else { 1.0 };
+            self.players[i].paddle.speed = speed * boost;
         }
 
-        self.player1.update(dt);
-        self.player2.update(dt);
+        if let GameState::Play = self.state {
+            self.arenas[self.arena].update(dt);
+            for (b, ball) in self.balls.iter_mut().enumerate() {
+                ball.update(dt);
+                self.juice.trail(b, ball.hitbox());
+            }
+        }
+        self.juice.update(dt);
+
+        for player in &mut self.players {
+            player.paddle.update(dt);
+        }
+        self.overlay.record_update(start.elapsed());
         Ok(())
     }
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
-        ggez::graphics::clear(ctx, [40.0 / 255.0, 45.0 / 255.0, 52.0 / 255.0, 1.0].into());
+        let start = Instant::now();
+        let court = [40.0 / 255.0, 45.0 / 255.0, 52.0 / 255.0, 1.0].into();
+        let fps = ctx.time.fps();
+        let mut frame = self.screen.begin(ctx, court)?;
 
         // UI
         match self.state {
+            // Blinks, on for most of each second.
+            _ if self.demo && self.idle.fract() < 0.7 => {
+                let text = self.fonts.text("PRESS ENTER", self.fonts.medium);
+                let white = graphics::Color::WHITE;
+                text::draw_aligned(&mut frame, &text, line(40.0), Align::Center, white);
+            }
+            _ if self.demo => (),
             GameState::Start => {
-                let text0 = ggez::graphics::Text::new((
-                    "Welcome to Pong!",
-                    self.fonts.font,
-                    self.fonts.small,
-                ));
-                let text1 = ggez::graphics::Text::new((
-                    "Press Enter to begin!",
-                    self.fonts.font,
-                    self.fonts.small,
-                ));
-                let x0 = center(16.0, self.fonts.small);
-                let pos0 = cgmath::Point2::new(x0, 15.0);
-                let x1 = center(21.0, self.fonts.small);
-                let pos1 = cgmath::Point2::new(x1, 40.0);
-                ggez::graphics::draw(ctx, &text0, (pos0,))?;
-                ggez::graphics::draw(ctx, &text1, (pos1,))?;
+                let white = graphics::Color::WHITE;
+                let text0 = self.fonts.text("Welcome to Pong!", self.fonts.small);
+                let text1 = self.fonts.text("Press Enter to begin!", self.fonts.small);
+                let text2 = self.fonts.text("Press O for options", self.fonts.small);
+                text::draw_aligned(&mut frame, &text0, line(15.0), Align::Center, white);
+                text::draw_aligned(&mut frame, &text1, line(40.0), Align::Center, white);
+                text::draw_aligned(&mut frame, &text2, line(65.0), Align::Center, white);
+                let msg = match self.challenge {
+                    _ if self.four => "Press your keys to take your side from the computer",
+                    Some(Challenge::Practice) => "Practice: keep the ball going against the wall",
+                    Some(Challenge::TimeAttack) => "Time attack: score all you can in 90 seconds",
+                    None => "Press T for a tournament",
+                };
+                let text3 = self.fonts.text(msg, self.fonts.small);
+                text::draw_aligned(&mut frame, &text3, line(90.0), Align::Center, white);
+                let msg = format!(
+                    "Press A to change the arena: {}",
+                    self.arenas[self.arena].name
+                );
+                let text4 = self.fonts.text(msg, self.fonts.small);
+                text::draw_aligned(&mut frame, &text4, line(115.0), Align::Center, white);
+            }
+            GameState::Options => {
+                let white = graphics::Color::WHITE;
+                let text = self.fonts.text("Options", self.fonts.medium);
+                text::draw_aligned(&mut frame, &text, line(15.0), Align::Center, white);
+
+                let items = option_items();
+                for (i, item) in items.iter().enumerate() {
+                    let color = if i == self.highlighted_option {
+                        ggez::graphics::Color::new(1.0, 1.0, 0.0, 1.0)
+                    } else {
+                        white
+                    };
+                    let label = item.label(&self.settings);
+                    let text = self.fonts.text(label, self.fonts.small);
+                    let area = line(100.0 + (i as f32) * 40.0);
+                    text::draw_aligned(&mut frame, &text, area, Align::Center, color);
+                }
+
+                let msg1 = if items[self.highlighted_option].needs_restart() {
+                    "Left/Right to change (applies on next launch), Escape to go back"
+                } else {
+                    "Left/Right to change, Escape to go back"
+                };
+                let area = graphics::Rect::new(
+                    VIRTUAL_WIDTH / 4.0,
+                    VIRTUAL_HEIGHT - 90.0,
+                    VIRTUAL_WIDTH / 2.0,
+                    0.0,
+                );
+                let (fonts, size) = (&self.fonts, self.fonts.small);
+                text::draw_wrapped(&mut frame, msg1, fonts, size, area, Align::Center, white);
+            }
+            GameState::Setup => {
+                let white = graphics::Color::WHITE;
+                let text = self.fonts.text("New tournament", self.fonts.medium);
+                text::draw_aligned(&mut frame, &text, line(15.0), Align::Center, white);
+                self.setup.render(&mut frame, &self.fonts);
+
+                let msg = "Type the names, Left/Right for who plays, Insert/Delete to add or \
+                           remove players, Enter to start, Escape to go back";
+                let area = graphics::Rect::new(
+                    VIRTUAL_WIDTH / 6.0,
+                    VIRTUAL_HEIGHT - 90.0,
+                    VIRTUAL_WIDTH * 2.0 / 3.0,
+                    0.0,
+                );
+                let (fonts, size) = (&self.fonts, self.fonts.small);
+                text::draw_wrapped(&mut frame, msg, fonts, size, area, Align::Center, white);
+            }
+            GameState::Bracket => {
+                let white = graphics::Color::WHITE;
+                let tournament = match &self.tournament {
+                    Some(tournament) => tournament,
+                    None => return self.screen.end(frame),
+                };
+                let (title, msg) = match tournament.champion() {
+                    Some(champion) => (
+                        format!("{} is the champion!", tournament.entrants[champion].name),
+                        "Press Enter to finish the tournament",
+                    ),
+                    None => (
+                        format!("Tournament, {}", tournament.format.name()),
+                        "Enter to play the next match, N for a new tournament, Escape for \
+                         the title",
+                    ),
+                };
+                let text = self.fonts.text(title, self.fonts.medium);
+                text::draw_aligned(&mut frame, &text, line(15.0), Align::Center, white);
+                tournament.render(&mut frame, &self.fonts);
+                let text = self.fonts.text(msg, self.fonts.small);
+                let area = line(VIRTUAL_HEIGHT - 60.0);
+                text::draw_aligned(&mut frame, &text, area, Align::Center, white);
             }
             GameState::Serve => {
-                let msg = format!("Player {}'s serve!", self.serving_player);
-                let len = msg.len();
-                let text = ggez::graphics::Text::new((msg, self.fonts.font, self.fonts.small));
-                let x = center(len as f32, self.fonts.small);
-                let pos = cgmath::Point2::new(x, 40.0);
-                ggez::graphics::draw(ctx, &text, (pos,))?;
+                let server = self.player_name(self.serving_player as usize - 1);
+                let msg = format!("{}'s serve!", server);
+                let text = self.fonts.text(msg, self.fonts.small);
+                let white = graphics::Color::WHITE;
+                text::draw_aligned(&mut frame, &text, line(40.0), Align::Center, white);
             }
             GameState::Done => {
-                let msg = format!("Player {} wins!", self.winning_player);
-                let len = msg.len();
-                let text = ggez::graphics::Text::new((msg, self.fonts.font, self.fonts.large));
-                let x = center(len as f32, self.fonts.large);
-                let pos = cgmath::Point2::new(x, 15.0);
-                ggez::graphics::draw(ctx, &text, (pos,))?;
+                let white = graphics::Color::WHITE;
+                let winner = self.player_name(self.winning_player as usize - 1);
+                let msg = match self.challenge {
+                    Some(_) if self.new_best => "Time's up! A new personal best!".to_string(),
+                    Some(_) => "Time's up!".to_string(),
+                    None => format!("{} wins!", winner),
+                };
+                let text = self.fonts.text(msg, self.fonts.medium);
+                text::draw_aligned(&mut frame, &text, line(15.0), Align::Center, white);
 
                 let msg1 = "Press Enter to restart!";
-                let len1 = msg1.len();
-                let text1 = ggez::graphics::Text::new((msg1, self.fonts.font, self.fonts.small));
-                let x1 = center(len1 as f32, self.fonts.small);
-                let pos1 = cgmath::Point2::new(x1, 60.0);
-                ggez::graphics::draw(ctx, &text1, (pos1,))?;
+                let text1 = self.fonts.text(msg1, self.fonts.small);
+                text::draw_aligned(&mut frame, &text1, line(75.0), Align::Center, white);
+
+                let names: Vec<String> = (0..self.players.len())
+                    .map(|i| self.player_name(i))
+                    .collect();
+                let top = VIRTUAL_HEIGHT / 2.0 + 60.0;
+                self.stats.render(&mut frame, &self.fonts, &names, top);
+                if let Some((rally, speed)) = self.records {
+                    let msg = format!("Records: rally {} hits, ball {:.0} pixels/s", rally, speed);
+                    let text = self.fonts.text(msg, self.fonts.small);
+                    let area = line(top + 5.0 * (self.fonts.small + 2.0) + 10.0);
+                    let gray = graphics::Color::new(0.7, 0.7, 0.7, 1.0);
+                    text::draw_aligned(&mut frame, &text, area, Align::Center, gray);
+                }
             }
             _ => (),
         }
 
-        self.display_score(ctx)?;
+        match self.state {
+            GameState::Options | GameState::Setup | GameState::Bracket => (),
+            _ => {
+                self.display_score(&mut frame);
 
-        self.player1.render(ctx)?;
-        self.player2.render(ctx)?;
-        self.ball.render(ctx)?;
+                self.display_effects(&mut frame);
 
-        self.display_fps(ctx)?;
+                let wall = graphics::Color::new(0.6, 0.6, 0.6, 1.0);
+                let arena = &self.arenas[self.arena];
+                arena.render(&mut frame);
+                for (i, player) in self.players.iter().enumerate() {
+                    if self.in_game(i) {
+                        for post in arena.posts(player.side, player.side.wall()) {
+                            frame.rect(post, wall);
+                        }
+                        let color = self.juice.tint(i, graphics::Color::WHITE);
+                        player.paddle.render(&mut frame, color);
+                    } else {
+                        frame.rect(player.side.wall(), wall);
+                    }
+                }
+                for effect in self.effects.iter().filter(|e| e.kind == Kind::Shield) {
+                    let wall = self.players[effect.owner].side.wall();
+                    frame.rect(wall, Kind::Shield.color());
+                }
+                if let Some(power_up) = &self.power_up {
+                    power_up.render(&mut frame);
+                    let text = self.fonts.text(power_up.kind.icon(), self.fonts.small);
+                    let dark = graphics::Color::new(0.1, 0.1, 0.1, 1.0);
+                    text::draw_aligned(&mut frame, &text, power_up.rect(), Align::Center, dark);
+                }
+                if !self.effects.iter().any(|e| e.kind == Kind::Invisible) {
+                    self.juice.render_trails(&mut frame, graphics::Color::WHITE);
+                    for (b, ball) in self.balls.iter().enumerate() {
+                        ball.render(&mut frame, self.juice.scale(b));
+                    }
+                }
+                self.juice.render_popups(&mut frame, &self.fonts);
+            }
+        }
 
-        ggez::graphics::present(ctx)
+        if let GameState::Paused = self.state {
+            let court = graphics::Rect::new(0.0, 0.0, VIRTUAL_WIDTH, VIRTUAL_HEIGHT);
+            frame.rect(court, graphics::Color::new(0.0, 0.0, 0.0, 0.6));
+            let white = graphics::Color::WHITE;
+            let text = self.fonts.text("Paused", self.fonts.medium);
+            text::draw_aligned(&mut frame, &text, line(200.0), Align::Center, white);
+            let tournament = self.playing.is_some();
+            for (i, item) in PAUSE_ITEMS.iter().enumerate() {
+                let color = if i == self.pause_item {
+                    graphics::Color::new(1.0, 1.0, 0.0, 1.0)
+                } else {
+                    white
+                };
+                let text = self.fonts.text(item.label(tournament), self.fonts.small);
+                let area = line(290.0 + i as f32 * 40.0);
+                text::draw_aligned(&mut frame, &text, area, Align::Center, color);
+            }
+        }
+
+        if self.settings.show_fps {
+            let pos = cgmath::Point2::new(30.0, 30.0);
+            let cache = &mut self.text_cache;
+            engine::debug::display_fps(&mut frame, cache, &self.fonts, fps, pos);
+        }
+
+        let four = self.four;
+        let mut bodies: Vec<&dyn Inspect> = self
+            .players
+            .iter()
+            .filter(|player| !four || player.lives > 0)
+            .map(|player| &player.paddle as &dyn Inspect)
+            .collect();
+        bodies.extend(self.balls.iter().map(|ball| ball as &dyn Inspect));
+        let info = Info {
+            state: self.state.name(),
+            entities: bodies.len(),
+            sounds: self.audio.playing(),
+        };
+        let panel = graphics::Rect::new(30.0, VIRTUAL_HEIGHT - 250.0, 480.0, 220.0);
+        let fonts = &self.fonts;
+        self.overlay
+            .render(&mut frame, fonts, &info, &bodies, panel);
+        let area = graphics::Rect::new(0.0, 0.0, VIRTUAL_WIDTH, VIRTUAL_HEIGHT / 2.0);
+        self.console.render(&mut frame, fonts, area);
+
+        self.screen.shake = self.juice.offset();
+        self.screen.end(frame)?;
+        self.overlay.record_draw(start.elapsed());
+        Ok(())
+    }
+
+    fn text_input_event(&mut self, _ctx: &mut Context, character: char) -> GameResult<()> {
+        if self.console.open {
+            self.console.text(character);
+        } else if let GameState::Setup = self.state {
+            self.setup.text(character);
+        }
+        Ok(())
+    }
+
+    fn mouse_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        _button: event::MouseButton,
+        _x: f32,
+        _y: f32,
+    ) -> GameResult<()> {
+        let _ = self.wake();
+        Ok(())
+    }
+
+    fn focus_event(&mut self, _ctx: &mut Context, gained: bool) -> GameResult<()> {
+        if !gained {
+            self.pause();
+        }
+        Ok(())
+    }
+
+    fn resize_event(&mut self, _ctx: &mut Context, width: f32, height: f32) -> GameResult<()> {
+        self.screen.resize(width, height);
+        Ok(())
     }
 
     fn key_down_event(
         &mut self,
         ctx: &mut Context,
-        keycode: KeyCode,
-        _keymod: KeyMods,
+        input: KeyInput,
         _repeat: bool,
-    ) {
+    ) -> GameResult<()> {
+        let keycode = match input.keycode {
+            Some(keycode) => keycode,
+            None => return Ok(()),
+        };
+        if self.wake() {
+            return Ok(());
+        }
+        if keycode == console::TOGGLE {
+            self.console.toggle();
+            return Ok(());
+        }
+        if self.console.open {
+            if let Some(line) = self.console.key(keycode, &COMMANDS) {
+                let result = console::execute(&COMMANDS, self, &line);
+                self.console.print(result);
+            }
+            return Ok(());
+        }
+        if keycode == KeyCode::F3 {
+            self.overlay.toggle();
+            return Ok(());
+        }
+        if let GameState::Options = self.state {
+            let items = option_items();
+            let len = items.len();
+            match keycode {
+                KeyCode::Escape | KeyCode::Return => {
+                    self.settings.save(GAME_ID);
+                    self.change(GameState::Start)
+                }
+                KeyCode::Up => self.highlighted_option = (self.highlighted_option + len - 1) % len,
+                KeyCode::Down => self.highlighted_option = (self.highlighted_option + 1) % len,
+                KeyCode::Left | KeyCode::Right => {
+                    let step = if keycode == KeyCode::Left { -1 } else { 1 };
+                    items[self.highlighted_option].adjust(&mut self.settings, step);
+                    self.audio.apply_settings(&self.settings);
+                    self.juice.apply_settings(&self.settings);
+                    self.audio.play(ctx, SoundKind::PaddleHit);
+                }
+                _ => {}
+            }
+            return Ok(());
+        }
+        match self.state {
+            GameState::Setup => {
+                match keycode {
+                    KeyCode::Escape => self.change(GameState::Start),
+                    KeyCode::Return => {
+                        let (format, entrants) = (self.setup.format, self.setup.entrants());
+                        let tournament = Tournament::new(format, entrants, &mut self.rng);
+                        save::store(GAME_ID, TOURNAMENT, &tournament);
+                        self.tournament = Some(tournament);
+                        self.change(GameState::Bracket)
+                    }
+                    _ => self.setup.key(keycode),
+                }
+                return Ok(());
+            }
+            GameState::Paused => {
+                let len = PAUSE_ITEMS.len();
+                match keycode {
+                    KeyCode::Escape | KeyCode::P => self.change(self.unpaused),
+                    KeyCode::Up => self.pause_item = (self.pause_item + len - 1) % len,
+                    KeyCode::Down => self.pause_item = (self.pause_item + 1) % len,
+                    KeyCode::Return => self.choose(ctx, PAUSE_ITEMS[self.pause_item]),
+                    _ => {}
+                }
+                return Ok(());
+            }
+            GameState::Bracket => {
+                match keycode {
+                    KeyCode::Escape => self.leave_tournament(),
+                    KeyCode::N => {
+                        self.setup.selected = 0;
+                        self.change(GameState::Setup)
+                    }
+                    KeyCode::Return => self.next_match(),
+                    _ => {}
+                }
+                return Ok(());
+            }
+            _ => {}
+        }
+
         match keycode {
-            KeyCode::Escape => ggez::event::quit(ctx),
+            KeyCode::Escape | KeyCode::P if self.in_match() => self.pause(),
+            KeyCode::Escape => ctx.request_quit(),
+            KeyCode::O => {
+                if let GameState::Start = self.state {
+                    self.highlighted_option = 0;
+                    self.change(GameState::Options)
+                }
+            }
+            KeyCode::T => {
+                let alone = self.four || self.challenge.is_some();
+                if let (GameState::Start, false) = (&self.state, alone) {
+                    self.open_tournament()
+                }
+            }
+            KeyCode::A => {
+                if let GameState::Start = self.state {
+                    self.arena = (self.arena + 1) % self.arenas.len();
+                    self.audio.play(ctx, SoundKind::PaddleHit);
+                }
+            }
             KeyCode::Return => match self.state {
-                GameState::Start => self.state = GameState::Serve,
-                GameState::Serve => self.state = GameState::Play,
+                GameState::Start => self.change(GameState::Serve),
+                GameState::Serve => {
+                    self.record(Event::Serve {
+                        player: self.serving_player,
+                        dx: self.balls[0].delta.x,
+                        dy: self.balls[0].delta.y,
+                    });
+                    self.change(GameState::Play)
+                }
+                GameState::Done if self.playing.is_some() => self.report(),
                 GameState::Done => {
-                    self.state = GameState::Serve;
-                    self.ball.reset();
-                    self.player1_score = 0;
-                    self.player2_score = 0;
+                    self.change(GameState::Serve);
+                    self.new_match();
 
-                    if self.winning_player == 1 {
+                    // The loser of a two-player match serves first.
+                    if !self.four && self.winning_player == 1 {
                         self.serving_player = 2
-                    } else {
-                        self.serving_player = 1
                     }
                 }
                 _ => {}
             },
             _ => {}
         }
+        Ok(())
     }
 }
 
+// Every file of `resources`, when built with the `embed` feature.
+const EMBEDDED: &[(&str, &[u8])] = include!(concat!(env!("OUT_DIR"), "/resources.rs"));
+
 fn main() {
-    let resource_dir = path::PathBuf::from("./resources");
-    println!("Resource dir = {}", resource_dir.display());
-    let mut c = conf::Conf::new();
-    c.window_mode.width = WIDTH;
-    c.window_mode.height = HEIGHT;
-
-    let (mut ctx, event_loop) = ContextBuilder::new("hello_ggez", "vinz")
-        .default_conf(c)
-        .window_setup(
-            ggez::conf::WindowSetup::default()
-                .title("Pong Test")
-                .vsync(false),
-        )
-        .add_resource_path(resource_dir)
-        .build()
-        .unwrap();
-    let rng = rand::thread_rng();
+    let about = "The classic paddle game, for up to four players.";
+    let options = Options::parse(GAME_ID, about, &FLAGS);
+    logging::init(GAME_ID, options.log.as_deref());
+    let resources = if cfg!(feature = "embed") {
+        Resources::Embedded(EMBEDDED)
+    } else {
+        let dir = options.resources.clone();
+        Resources::locate(GAME_ID, dir, env!("CARGO_MANIFEST_DIR"))
+    };
+    log::info!("resources: {}", resources);
+    let settings = Settings::load(GAME_ID);
+
+    let (mut ctx, event_loop) =
+        engine::context_builder(GAME_ID, "Pong Test", &settings, &resources)
+            .window_mode(options.window_mode(&settings))
+            .build()
+            .unwrap();
+    // Logged so that any game can be played again with --seed.
+    let seed = options.seed.unwrap_or_else(rand::random);
+    log::info!("seed: {}", seed);
 
-    let state = State::new(&mut ctx, rng).unwrap();
+    let size = (VIRTUAL_WIDTH, VIRTUAL_HEIGHT);
+    let launcher = Launcher::new(&mut ctx, resources, size, false, move |ctx, assets| {
+        State::new(ctx, seed, settings, &options, &assets)
+    })
+    .unwrap();
 
-    event::run(ctx, event_loop, state)
+    event::run(ctx, event_loop, launcher)
 }
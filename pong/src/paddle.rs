@@ -1,37 +1,94 @@
 use ggez::graphics;
-use ggez::graphics::DrawParam;
-use ggez::Context;
-use ggez::GameResult;
 
-use cgmath::Point2;
+use engine::debug::Inspect;
+use engine::render::Renderer;
+
+use cgmath::{Point2, Vector2};
+
+/// The direction a paddle moves in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Axis {
+    Vertical,
+    Horizontal,
+}
 
 pub struct Paddle {
     pub xy: Point2<f32>,
     pub size: Point2<f32>,
-    pub dy: f32,
+    pub axis: Axis,
+    // Along the axis, towards the bottom or the right when positive.
+    pub speed: f32,
 }
 
 impl Paddle {
-    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Paddle {
+    pub fn new(x: f32, y: f32, width: f32, height: f32, axis: Axis) -> Paddle {
         let xy = Point2::new(x, y);
         let size = Point2::new(width, height);
-        Paddle { xy, size, dy: 0.0 }
+        Paddle {
+            xy,
+            size,
+            axis,
+            speed: 0.0,
+        }
+    }
+
+    /// Makes it `length` long along its axis, around the same middle.
+    pub fn set_length(&mut self, length: f32) {
+        let (position, size) = match self.axis {
+            Axis::Vertical => (&mut self.xy.y, &mut self.size.y),
+            Axis::Horizontal => (&mut self.xy.x, &mut self.size.x),
+        };
+        *position += (*size - length) / 2.0;
+        *size = length;
     }
 
     pub fn update(&mut self, dt: f32) {
-        if self.dy < 0.0 {
-            let limit = 0.0 as f32;
-            self.xy.y = limit.max(self.xy.y + self.dy * dt)
-        } else {
-            let h = super::HEIGHT - self.size.y;
-            self.xy.y = h.min(self.xy.y + self.dy * dt)
-        }
+        let (position, limit) = match self.axis {
+            Axis::Vertical => (&mut self.xy.y, super::VIRTUAL_HEIGHT - self.size.y),
+            Axis::Horizontal => (&mut self.xy.x, super::VIRTUAL_WIDTH - self.size.x),
+        };
+        *position = (*position + self.speed * dt).clamp(0.0, limit)
     }
 
-    pub fn render(&self, ctx: &mut Context) -> GameResult<()> {
+    pub fn render(&self, renderer: &mut dyn Renderer, color: graphics::Color) {
         let rect = graphics::Rect::new(self.xy.x, self.xy.y, self.size.x, self.size.y);
-        let mesh =
-            graphics::Mesh::new_rectangle(ctx, graphics::DrawMode::fill(), rect, graphics::Color::WHITE)?;
-        graphics::draw(ctx, &mesh, DrawParam::default())
+        renderer.rect(rect, color)
+    }
+}
+
+impl Inspect for Paddle {
+    fn hitbox(&self) -> graphics::Rect {
+        graphics::Rect::new(self.xy.x, self.xy.y, self.size.x, self.size.y)
+    }
+
+    fn velocity(&self) -> Option<Vector2<f32>> {
+        match self.axis {
+            Axis::Vertical => Some(Vector2::new(0.0, self.speed)),
+            Axis::Horizontal => Some(Vector2::new(self.speed, 0.0)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Axis, Paddle};
+
+    #[test]
+    fn stays_in_the_court_along_its_axis() {
+        let mut paddle = Paddle::new(600.0, 30.0, 59.0, 15.0, Axis::Horizontal);
+        paddle.speed = 200.0;
+        paddle.update(10.0);
+        assert_eq!((paddle.xy.x, paddle.xy.y), (1280.0 - 59.0, 30.0));
+        paddle.speed = -200.0;
+        paddle.update(10.0);
+        assert_eq!((paddle.xy.x, paddle.xy.y), (0.0, 30.0));
+    }
+
+    #[test]
+    fn grows_around_its_middle() {
+        let mut paddle = Paddle::new(30.0, 90.0, 15.0, 60.0, Axis::Vertical);
+        paddle.set_length(90.0);
+        assert_eq!((paddle.xy.y, paddle.size.y), (75.0, 90.0));
+        assert_eq!((paddle.xy.x, paddle.size.x), (30.0, 15.0));
     }
 }
@@ -0,0 +1,199 @@
+//! Power-ups appearing in the middle of the court, claimed by whoever last
+//! hit the ball that goes through them.
+
+use std::collections::BTreeMap;
+
+use rand::Rng;
+use serde::Deserialize;
+
+use ggez::graphics::{Color, Rect};
+
+use cgmath::Point2;
+
+use engine::render::Renderer;
+
+use super::{VIRTUAL_HEIGHT, VIRTUAL_WIDTH};
+
+const SIZE: f32 = 24.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    /// A longer paddle.
+    Grow,
+    /// Shorter paddles for the others.
+    Shrink,
+    /// A faster paddle.
+    Fast,
+    /// Another ball, going the other way across.
+    Split,
+    /// The balls they hit bend on their way.
+    Curve,
+    /// Nobody sees the balls.
+    Invisible,
+    /// A wall behind their paddle.
+    Shield,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 7] = [
+        Kind::Grow,
+        Kind::Shrink,
+        Kind::Fast,
+        Kind::Split,
+        Kind::Curve,
+        Kind::Invisible,
+        Kind::Shield,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Kind> {
+        Kind::ALL.iter().copied().find(|kind| kind.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::Grow => "grow",
+            Kind::Shrink => "shrink",
+            Kind::Fast => "fast",
+            Kind::Split => "split",
+            Kind::Curve => "curve",
+            Kind::Invisible => "invisible",
+            Kind::Shield => "shield",
+        }
+    }
+
+    /// Seconds it lasts, or 0 when it happens at once.
+    pub fn duration(self) -> f32 {
+        match self {
+            Kind::Grow | Kind::Shrink => 10.0,
+            Kind::Fast | Kind::Shield => 8.0,
+            Kind::Curve => 6.0,
+            Kind::Invisible => 1.0,
+            Kind::Split => 0.0,
+        }
+    }
+
+    /// Shown on the court and next to the score of its owner.
+    pub fn icon(self) -> &'static str {
+        match self {
+            Kind::Grow => "+",
+            Kind::Shrink => "-",
+            Kind::Fast => ">",
+            Kind::Split => "2",
+            Kind::Curve => "~",
+            Kind::Invisible => "?",
+            Kind::Shield => "#",
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            Kind::Grow => Color::new(0.4, 0.9, 0.4, 1.0),
+            Kind::Shrink => Color::new(0.9, 0.4, 0.4, 1.0),
+            Kind::Fast => Color::new(1.0, 0.8, 0.2, 1.0),
+            Kind::Split => Color::new(0.4, 0.8, 1.0, 1.0),
+            Kind::Curve => Color::new(0.8, 0.5, 1.0, 1.0),
+            Kind::Invisible => Color::new(0.6, 0.6, 0.6, 1.0),
+            Kind::Shield => Color::new(0.3, 0.5, 1.0, 1.0),
+        }
+    }
+}
+
+/// How often power-ups come, from `powerups.toml`.
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Table {
+    /// Seconds of play between one power-up and the next.
+    pub interval: f32,
+    // How likely each kind is, against the others. Missing ones never come.
+    weights: BTreeMap<Kind, u32>,
+}
+
+impl Table {
+    pub fn parse(content: &str) -> Result<Table, String> {
+        let table: Table = toml::from_str(content).map_err(|e| e.to_string())?;
+        if table.interval <= 0.0 {
+            return Err("the interval must be positive".to_string());
+        }
+        Ok(table)
+    }
+
+    /// A kind at random, by weight, or none if they are all 0.
+    pub fn pick(&self, rng: &mut impl Rng) -> Option<Kind> {
+        let total: u32 = self.weights.values().sum();
+        if total == 0 {
+            return None;
+        }
+        let mut roll = rng.gen_range(0..total);
+        for (&kind, &weight) in &self.weights {
+            if roll < weight {
+                return Some(kind);
+            }
+            roll -= weight;
+        }
+        None
+    }
+}
+
+/// A power-up waiting on the court.
+pub struct PowerUp {
+    pub kind: Kind,
+    pub xy: Point2<f32>,
+}
+
+impl PowerUp {
+    /// Somewhere around the middle of the court.
+    pub fn new(kind: Kind, rng: &mut impl Rng) -> PowerUp {
+        let x = VIRTUAL_WIDTH / 2.0 + rng.gen_range(-120.0..120.0);
+        let y = VIRTUAL_HEIGHT / 2.0 + rng.gen_range(-200.0..200.0);
+        PowerUp {
+            kind,
+            xy: Point2::new(x - SIZE / 2.0, y - SIZE / 2.0),
+        }
+    }
+
+    pub fn rect(&self) -> Rect {
+        Rect::new(self.xy.x, self.xy.y, SIZE, SIZE)
+    }
+
+    pub fn render(&self, renderer: &mut dyn Renderer) {
+        renderer.rect(self.rect(), self.kind.color())
+    }
+}
+
+/// A power-up claimed by player `owner`, for `left` more seconds.
+pub struct Effect {
+    pub kind: Kind,
+    pub owner: usize,
+    pub left: f32,
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::{Kind, Table};
+
+    #[test]
+    fn picks_only_the_kinds_with_a_weight() {
+        let table =
+            Table::parse("interval = 5.0\n[weights]\ngrow = 1\nshield = 3\nsplit = 0").unwrap();
+        let mut rng = StdRng::seed_from_u64(1);
+        let picks: Vec<Kind> = (0..100).filter_map(|_| table.pick(&mut rng)).collect();
+        assert_eq!(picks.len(), 100);
+        let shields = picks.iter().filter(|&&kind| kind == Kind::Shield).count();
+        assert!(shields > 50 && shields < 100, "{} shields", shields);
+        assert!(picks.iter().all(|&kind| kind != Kind::Split));
+    }
+
+    #[test]
+    fn rejects_unknown_kinds() {
+        assert!(Table::parse("interval = 5.0\n[weights]\nlaser = 1").is_err());
+        assert!(Table::parse("interval = 0.0\n[weights]").is_err());
+        let none = Table::parse("interval = 5.0\n[weights]").unwrap();
+        assert_eq!(none.pick(&mut StdRng::seed_from_u64(1)), None);
+        assert_eq!(Kind::from_name("shield"), Some(Kind::Shield));
+        assert_eq!(Kind::from_name("laser"), None);
+    }
+}
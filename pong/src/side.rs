@@ -0,0 +1,101 @@
+use ggez::graphics;
+use ggez::input::keyboard::KeyCode;
+
+use cgmath::Vector2;
+
+use super::paddle::{Axis, Paddle};
+use super::{PADDLE_LENGTH, VIRTUAL_HEIGHT, VIRTUAL_WIDTH};
+
+/// A wall of the court, defended by a player's paddle or solid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+impl Side {
+    /// In the order of the players.
+    pub const ALL: [Side; 4] = [Side::Left, Side::Right, Side::Top, Side::Bottom];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Side::Left => "left",
+            Side::Right => "right",
+            Side::Top => "top",
+            Side::Bottom => "bottom",
+        }
+    }
+
+    /// How the paddle of this side moves.
+    pub fn axis(self) -> Axis {
+        match self {
+            Side::Left | Side::Right => Axis::Vertical,
+            Side::Top | Side::Bottom => Axis::Horizontal,
+        }
+    }
+
+    /// Points away from the wall, into the court.
+    pub fn inward(self) -> Vector2<f32> {
+        match self {
+            Side::Left => Vector2::new(1.0, 0.0),
+            Side::Right => Vector2::new(-1.0, 0.0),
+            Side::Top => Vector2::new(0.0, 1.0),
+            Side::Bottom => Vector2::new(0.0, -1.0),
+        }
+    }
+
+    /// Along the wall.
+    pub fn across(self) -> Vector2<f32> {
+        match self.axis() {
+            Axis::Vertical => Vector2::new(0.0, 1.0),
+            Axis::Horizontal => Vector2::new(1.0, 0.0),
+        }
+    }
+
+    /// The keys moving the paddle of this side up or left, then down or
+    /// right.
+    pub fn keys(self) -> [KeyCode; 2] {
+        match self {
+            Side::Left => [KeyCode::W, KeyCode::S],
+            Side::Right => [KeyCode::Up, KeyCode::Down],
+            Side::Top => [KeyCode::C, KeyCode::V],
+            Side::Bottom => [KeyCode::N, KeyCode::M],
+        }
+    }
+
+    /// The paddle of this side, where a match starts.
+    pub fn paddle(self) -> Paddle {
+        let (w, h, l) = (VIRTUAL_WIDTH, VIRTUAL_HEIGHT, PADDLE_LENGTH);
+        match self {
+            Side::Left => Paddle::new(30.0, 90.0, 15.0, l, Axis::Vertical),
+            Side::Right => Paddle::new(w - 30.0, h - 90.0, 15.0, l, Axis::Vertical),
+            Side::Top => Paddle::new(w / 2.0 - 30.0, 30.0, l, 15.0, Axis::Horizontal),
+            Side::Bottom => Paddle::new(w / 2.0 - 30.0, h - 30.0, l, 15.0, Axis::Horizontal),
+        }
+    }
+
+    /// Past the edge of this side, for balls to bounce off. It goes on
+    /// beyond the corners, so that balls can't slip past them.
+    pub fn boundary(self) -> graphics::Rect {
+        let (w, h, depth) = (VIRTUAL_WIDTH, VIRTUAL_HEIGHT, 100.0);
+        match self {
+            Side::Left => graphics::Rect::new(-depth, -depth, depth, h + 2.0 * depth),
+            Side::Right => graphics::Rect::new(w, -depth, depth, h + 2.0 * depth),
+            Side::Top => graphics::Rect::new(-depth, -depth, w + 2.0 * depth, depth),
+            Side::Bottom => graphics::Rect::new(-depth, h, w + 2.0 * depth, depth),
+        }
+    }
+
+    /// The wall drawn on this side once nobody defends it.
+    pub fn wall(self) -> graphics::Rect {
+        let (w, h, thickness) = (VIRTUAL_WIDTH, VIRTUAL_HEIGHT, 6.0);
+        match self {
+            Side::Left => graphics::Rect::new(0.0, 0.0, thickness, h),
+            Side::Right => graphics::Rect::new(w - thickness, 0.0, thickness, h),
+            Side::Top => graphics::Rect::new(0.0, 0.0, w, thickness),
+            Side::Bottom => graphics::Rect::new(0.0, h - thickness, w, thickness),
+        }
+    }
+}
@@ -0,0 +1,140 @@
+//! What happened during a match, shown once it is over and, with
+//! `--history`, kept in a file to compare matches across sessions.
+
+use serde::{Deserialize, Serialize};
+
+use ggez::graphics::{Color, Rect};
+
+use engine::assets::Fonts;
+use engine::render::Renderer;
+use engine::text::{self, Align};
+
+use super::VIRTUAL_WIDTH;
+
+// Where the matches are kept, in the data directory.
+pub const HISTORY: &str = "history.jsonl";
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Stats {
+    /// Paddle hits of the longest rally.
+    pub longest_rally: u32,
+    /// Paddle hits of each player.
+    pub hits: Vec<u32>,
+    /// The fastest a ball went, in pixels per second.
+    pub top_speed: f32,
+    /// Points each player won on their own serve.
+    pub serve_points: Vec<u32>,
+    /// Seconds of serves and play.
+    pub time: f32,
+}
+
+impl Stats {
+    pub fn new(players: usize) -> Stats {
+        Stats {
+            hits: vec![0; players],
+            serve_points: vec![0; players],
+            ..Stats::default()
+        }
+    }
+
+    /// Player `i` hit a ball, `rally` hits into the rally, sending it at
+    /// `speed`.
+    pub fn hit(&mut self, i: usize, rally: u32, speed: f32) {
+        self.hits[i] += 1;
+        self.longest_rally = self.longest_rally.max(rally);
+        self.top_speed = self.top_speed.max(speed);
+    }
+
+    /// Player `i` let a ball past on the serve of `server`.
+    pub fn concede(&mut self, i: usize, server: usize) {
+        if i != server {
+            self.serve_points[server] += 1;
+        }
+    }
+
+    /// A line per statistic, centered from `y`, with the names of the
+    /// players.
+    pub fn render(&self, renderer: &mut dyn Renderer, fonts: &Fonts, names: &[String], y: f32) {
+        let per_player = |counts: &[u32]| {
+            let counts = names
+                .iter()
+                .zip(counts)
+                .map(|(name, n)| format!("{} {}", name, n));
+            counts.collect::<Vec<_>>().join(", ")
+        };
+        let seconds = self.time as u32;
+        let lines = [
+            format!("Match time {}:{:02}", seconds / 60, seconds % 60),
+            format!("Longest rally {} hits", self.longest_rally),
+            format!("Fastest ball {:.0} pixels/s", self.top_speed),
+            format!("Hits: {}", per_player(&self.hits)),
+            format!("Points on serve: {}", per_player(&self.serve_points)),
+        ];
+        let size = fonts.small;
+        for (i, line) in lines.iter().enumerate() {
+            let text = fonts.text(line.as_str(), size);
+            let area = Rect::new(0.0, y + i as f32 * (size + 2.0), VIRTUAL_WIDTH, 0.0);
+            text::draw_aligned(renderer, &text, area, Align::Center, Color::WHITE);
+        }
+    }
+}
+
+/// A match of the history.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    /// When it ended, in seconds since the Unix epoch.
+    pub end: u64,
+    pub mode: String,
+    pub winner: String,
+    /// Points in a two-player match, lives left in a four-player one.
+    pub scores: Vec<u32>,
+    #[serde(flatten)]
+    pub stats: Stats,
+}
+
+/// The longest rally and the fastest ball of all the matches.
+pub fn records<'a>(entries: impl IntoIterator<Item = &'a Stats>) -> (u32, f32) {
+    entries.into_iter().fold((0, 0.0), |(rally, speed), stats| {
+        (rally.max(stats.longest_rally), speed.max(stats.top_speed))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{records, Entry, Stats};
+
+    #[test]
+    fn counts_hits_rallies_and_serves() {
+        let mut stats = Stats::new(2);
+        stats.hit(0, 1, 200.0);
+        stats.hit(1, 2, 250.0);
+        stats.hit(0, 3, 240.0);
+        stats.concede(0, 1);
+        stats.hit(1, 1, 180.0);
+        stats.concede(1, 1);
+        assert_eq!(stats.hits, [2, 2]);
+        assert_eq!(stats.longest_rally, 3);
+        assert_eq!(stats.top_speed, 250.0);
+        assert_eq!(stats.serve_points, [0, 1]);
+    }
+
+    #[test]
+    fn keeps_the_records_of_the_history() {
+        let mut stats = Stats::new(2);
+        stats.hit(0, 12, 300.0);
+        let entry = Entry {
+            end: 1_700_000_000,
+            mode: "2p".to_string(),
+            winner: "Player 1".to_string(),
+            scores: vec![10, 4],
+            stats: stats.clone(),
+        };
+        let line = serde_json::to_string(&entry).unwrap();
+        assert!(line.contains("\"longest_rally\":12"), "{}", line);
+        assert_eq!(serde_json::from_str::<Entry>(&line).unwrap(), entry);
+
+        let mut faster = Stats::new(2);
+        faster.hit(1, 4, 420.0);
+        assert_eq!(records(vec![&stats, &faster]), (12, 420.0));
+    }
+}
@@ -0,0 +1,519 @@
+//! Local tournaments: the players entered, their bracket and the results so
+//! far, saved after every match so that a tournament can be resumed.
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use ggez::graphics::Color;
+use ggez::input::keyboard::KeyCode;
+
+use cgmath::Point2;
+
+use engine::assets::Fonts;
+use engine::render::Renderer;
+
+use super::{VIRTUAL_HEIGHT, VIRTUAL_WIDTH};
+
+pub const MIN_ENTRANTS: usize = 4;
+pub const MAX_ENTRANTS: usize = 16;
+const NAME_LENGTH: usize = 12;
+
+/// How well the computer plays.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+        }
+    }
+
+    /// The part of the paddle speed the computer moves at.
+    pub fn skill(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.5,
+            Difficulty::Normal => 0.8,
+            Difficulty::Hard => 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Entrant {
+    pub name: String,
+    /// How well the computer plays for them, or `None` for a human.
+    pub computer: Option<Difficulty>,
+}
+
+impl Entrant {
+    fn kind(&self) -> String {
+        match self.computer {
+            Some(difficulty) => format!("computer, {}", difficulty.name()),
+            None => "human".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+    /// Winners go on to the next round until one is left.
+    SingleElimination,
+    /// Everyone plays everyone, the most wins takes it.
+    RoundRobin,
+}
+
+impl Format {
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::SingleElimination => "single elimination",
+            Format::RoundRobin => "round robin",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Match {
+    /// The entrants playing, by index, once they are known. A single one in
+    /// the first round of an elimination has a bye.
+    pub players: [Option<usize>; 2],
+    pub score: Option<[u32; 2]>,
+    pub winner: Option<usize>,
+}
+
+impl Match {
+    fn new(first: Option<usize>, second: Option<usize>) -> Match {
+        Match {
+            players: [first, second],
+            score: None,
+            winner: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Tournament {
+    pub format: Format,
+    pub entrants: Vec<Entrant>,
+    /// The matches of each round, played in order.
+    pub rounds: Vec<Vec<Match>>,
+}
+
+impl Tournament {
+    /// A bracket for the entrants, drawn at random.
+    pub fn new(format: Format, entrants: Vec<Entrant>, rng: &mut impl Rng) -> Tournament {
+        let mut order: Vec<usize> = (0..entrants.len()).collect();
+        order.shuffle(rng);
+        let rounds = match format {
+            Format::SingleElimination => elimination(&order),
+            Format::RoundRobin => round_robin(&order),
+        };
+        let mut tournament = Tournament {
+            format,
+            entrants,
+            rounds,
+        };
+        // Players with a bye go through to the second round.
+        for index in 0..tournament.rounds[0].len() {
+            if let [Some(player), None] = tournament.rounds[0][index].players {
+                tournament.advance(0, index, player);
+            }
+        }
+        tournament
+    }
+
+    /// The round and index of the next match to play.
+    pub fn next(&self) -> Option<(usize, usize)> {
+        self.rounds.iter().enumerate().find_map(|(round, matches)| {
+            let ready = |m: &Match| m.winner.is_none() && m.players.iter().all(Option::is_some);
+            matches.iter().position(ready).map(|index| (round, index))
+        })
+    }
+
+    /// The two entrants of a match.
+    pub fn players(&self, round: usize, index: usize) -> [&Entrant; 2] {
+        let m = &self.rounds[round][index];
+        let entrant = |i: usize| &self.entrants[m.players[i].unwrap_or(0)];
+        [entrant(0), entrant(1)]
+    }
+
+    /// Records the points of both players of a match.
+    pub fn report(&mut self, round: usize, index: usize, score: [u32; 2]) {
+        let m = &mut self.rounds[round][index];
+        m.score = Some(score);
+        let winner = m.players[if score[1] > score[0] { 1 } else { 0 }];
+        if let Some(winner) = winner {
+            self.advance(round, index, winner);
+        }
+    }
+
+    fn advance(&mut self, round: usize, index: usize, winner: usize) {
+        self.rounds[round][index].winner = Some(winner);
+        if let Format::SingleElimination = self.format {
+            if let Some(next) = self.rounds.get_mut(round + 1) {
+                next[index / 2].players[index % 2] = Some(winner);
+            }
+        }
+    }
+
+    /// The number of matches won by each entrant, and their points scored
+    /// minus conceded, best first.
+    pub fn standings(&self) -> Vec<(usize, u32, i32)> {
+        let mut standings: Vec<(usize, u32, i32)> =
+            (0..self.entrants.len()).map(|i| (i, 0, 0)).collect();
+        for m in self.rounds.iter().flatten() {
+            if let (Some(winner), Some(score)) = (m.winner, m.score) {
+                standings[winner].1 += 1;
+                for (side, player) in m.players.iter().enumerate() {
+                    if let Some(player) = *player {
+                        standings[player].2 += score[side] as i32 - score[1 - side] as i32;
+                    }
+                }
+            }
+        }
+        standings.sort_by_key(|&(i, wins, points)| (std::cmp::Reverse((wins, points)), i));
+        standings
+    }
+
+    /// Who won, once every match was played.
+    pub fn champion(&self) -> Option<usize> {
+        if self.next().is_some() {
+            return None;
+        }
+        match self.format {
+            Format::SingleElimination => self.rounds.last()?.first()?.winner,
+            Format::RoundRobin => self.standings().first().map(|&(i, _, _)| i),
+        }
+    }
+
+    /// The bracket of an elimination, or the standings of a round robin,
+    /// with the next match highlighted.
+    pub fn render(&self, renderer: &mut dyn Renderer, fonts: &Fonts) {
+        match self.format {
+            Format::SingleElimination => self.render_bracket(renderer, fonts),
+            Format::RoundRobin => self.render_standings(renderer, fonts),
+        }
+    }
+
+    fn name(&self, player: Option<usize>) -> &str {
+        player.map_or("...", |i| &self.entrants[i].name)
+    }
+
+    // A column per round, each match centered on the two it comes from.
+    fn render_bracket(&self, renderer: &mut dyn Renderer, fonts: &Fonts) {
+        let (top, height) = (90.0, VIRTUAL_HEIGHT - 200.0);
+        let width = VIRTUAL_WIDTH / self.rounds.len() as f32;
+        let size = fonts.small;
+        for (round, matches) in self.rounds.iter().enumerate() {
+            let x = 40.0 + round as f32 * width;
+            let step = height / matches.len() as f32;
+            for (index, m) in matches.iter().enumerate() {
+                let next = self.next() == Some((round, index));
+                let y = top + (index as f32 + 0.5) * step - size;
+                for (side, &player) in m.players.iter().enumerate() {
+                    let color = match m.winner {
+                        _ if next => Color::new(1.0, 1.0, 0.0, 1.0),
+                        Some(winner) if Some(winner) != player => Color::new(0.5, 0.5, 0.5, 1.0),
+                        _ => Color::WHITE,
+                    };
+                    let line = match (m.score, round, player) {
+                        (Some(score), _, _) => format!("{} {}", self.name(player), score[side]),
+                        // A bye.
+                        (None, 0, None) => "-".to_string(),
+                        _ => self.name(player).to_string(),
+                    };
+                    let text = fonts.text(line, size);
+                    let pos = Point2::new(x, y + side as f32 * size);
+                    renderer.text(&text, pos, color);
+                }
+            }
+        }
+    }
+
+    // A line per entrant, with their wins and points, and the next match.
+    fn render_standings(&self, renderer: &mut dyn Renderer, fonts: &Fonts) {
+        let size = fonts.small;
+        let played = self.rounds.iter().flatten().filter(|m| m.winner.is_some());
+        let total = self.rounds.iter().map(Vec::len).sum::<usize>();
+        let progress = format!("{} of {} matches played", played.count(), total);
+        let text = fonts.text(progress, size);
+        renderer.text(
+            &text,
+            Point2::new(VIRTUAL_WIDTH / 2.0 + 40.0, 90.0),
+            Color::WHITE,
+        );
+        if let Some((round, index)) = self.next() {
+            let [first, second] = self.players(round, index);
+            let line = format!("Next: {} vs {}", first.name, second.name);
+            let text = fonts.text(line, size);
+            let yellow = Color::new(1.0, 1.0, 0.0, 1.0);
+            renderer.text(
+                &text,
+                Point2::new(VIRTUAL_WIDTH / 2.0 + 40.0, 130.0),
+                yellow,
+            );
+        }
+        for (rank, &(i, wins, points)) in self.standings().iter().enumerate() {
+            let name = &self.entrants[i].name;
+            let line = format!(
+                "{:>2}. {:<12} {:>2} wins {:>+4}",
+                rank + 1,
+                name,
+                wins,
+                points
+            );
+            let text = fonts.text(line, size);
+            let y = 90.0 + rank as f32 * (size + 4.0);
+            renderer.text(&text, Point2::new(80.0, y), Color::WHITE);
+        }
+    }
+}
+
+// The first round pairs the top half of the draw with the bottom half, with
+// byes for the last ones when the entrants don't fill the bracket. The next
+// rounds are filled in as matches are won.
+fn elimination(order: &[usize]) -> Vec<Vec<Match>> {
+    let size = order.len().next_power_of_two();
+    let half = size / 2;
+    let full = order.len() - half;
+    let first = (0..half)
+        .map(|k| {
+            Match::new(
+                Some(order[k]),
+                order.get(half + k).filter(|_| k < full).copied(),
+            )
+        })
+        .collect();
+    let mut rounds = vec![first];
+    let mut matches = half / 2;
+    while matches > 0 {
+        rounds.push((0..matches).map(|_| Match::new(None, None)).collect());
+        matches /= 2;
+    }
+    rounds
+}
+
+// Everyone plays everyone once, with the circle method: one player stays
+// put while the others turn around them, so that nobody plays twice in a
+// round. With an odd number, whoever faces the empty seat sits out.
+fn round_robin(order: &[usize]) -> Vec<Vec<Match>> {
+    let mut seats: Vec<Option<usize>> = order.iter().copied().map(Some).collect();
+    if seats.len() % 2 == 1 {
+        seats.push(None);
+    }
+    let n = seats.len();
+    let mut rounds = Vec::new();
+    for _ in 0..n - 1 {
+        let round = (0..n / 2)
+            .filter_map(|k| match (seats[k], seats[n - 1 - k]) {
+                (Some(a), Some(b)) => Some(Match::new(Some(a), Some(b))),
+                _ => None,
+            })
+            .collect();
+        rounds.push(round);
+        seats[1..].rotate_right(1);
+    }
+    rounds
+}
+
+/// The entrants of a new tournament, while they are entered.
+pub struct Setup {
+    pub format: Format,
+    pub entrants: Vec<Entrant>,
+    /// The line selected: the format first, then the entrants. It starts on
+    /// the format, so that the key opening the setup doesn't type a name.
+    pub selected: usize,
+}
+
+impl Setup {
+    /// Two humans against two computers.
+    pub fn new() -> Setup {
+        let entrant = |i: usize, computer| Entrant {
+            name: format!("Player {}", i),
+            computer,
+        };
+        Setup {
+            format: Format::SingleElimination,
+            entrants: vec![
+                entrant(1, None),
+                entrant(2, None),
+                entrant(3, Some(Difficulty::Normal)),
+                entrant(4, Some(Difficulty::Hard)),
+            ],
+            selected: 0,
+        }
+    }
+
+    /// Moves between the lines, changes the format or who plays, and adds
+    /// or removes entrants. Enter is left to the caller.
+    pub fn key(&mut self, key: KeyCode) {
+        let lines = self.entrants.len() + 1;
+        match key {
+            KeyCode::Up => self.selected = (self.selected + lines - 1) % lines,
+            KeyCode::Down => self.selected = (self.selected + 1) % lines,
+            KeyCode::Left | KeyCode::Right if self.selected == 0 => {
+                self.format = match self.format {
+                    Format::SingleElimination => Format::RoundRobin,
+                    Format::RoundRobin => Format::SingleElimination,
+                }
+            }
+            KeyCode::Left | KeyCode::Right => {
+                let kinds = [
+                    None,
+                    Some(Difficulty::Easy),
+                    Some(Difficulty::Normal),
+                    Some(Difficulty::Hard),
+                ];
+                let entrant = &mut self.entrants[self.selected - 1];
+                let current = kinds.iter().position(|&k| k == entrant.computer);
+                let step = if key == KeyCode::Left {
+                    kinds.len() - 1
+                } else {
+                    1
+                };
+                entrant.computer = kinds[(current.unwrap_or(0) + step) % kinds.len()];
+            }
+            KeyCode::Back if self.selected > 0 => {
+                let _ = self.entrants[self.selected - 1].name.pop();
+            }
+            KeyCode::Insert if self.entrants.len() < MAX_ENTRANTS => {
+                let name = format!("Player {}", self.entrants.len() + 1);
+                self.entrants.push(Entrant {
+                    name,
+                    computer: Some(Difficulty::Normal),
+                });
+                self.selected = self.entrants.len();
+            }
+            KeyCode::Delete if self.selected > 0 && self.entrants.len() > MIN_ENTRANTS => {
+                let _ = self.entrants.remove(self.selected - 1);
+                self.selected = self.selected.min(self.entrants.len());
+            }
+            _ => {}
+        }
+    }
+
+    /// Types into the name of the selected entrant.
+    pub fn text(&mut self, c: char) {
+        if self.selected == 0 || c.is_control() {
+            return;
+        }
+        let name = &mut self.entrants[self.selected - 1].name;
+        if name.chars().count() < NAME_LENGTH {
+            name.push(c);
+        }
+    }
+
+    /// The entrants, with a name for those left without one.
+    pub fn entrants(&self) -> Vec<Entrant> {
+        let mut entrants = self.entrants.clone();
+        for (i, entrant) in entrants.iter_mut().enumerate() {
+            if entrant.name.trim().is_empty() {
+                entrant.name = format!("Player {}", i + 1);
+            }
+        }
+        entrants
+    }
+
+    pub fn render(&self, renderer: &mut dyn Renderer, fonts: &Fonts) {
+        let size = fonts.small;
+        let lines = std::iter::once(format!("Format: {}", self.format.name())).chain(
+            self.entrants
+                .iter()
+                .map(|entrant| format!("{:<12}  {}", entrant.name, entrant.kind())),
+        );
+        for (i, line) in lines.enumerate() {
+            let color = if i == self.selected {
+                Color::new(1.0, 1.0, 0.0, 1.0)
+            } else {
+                Color::WHITE
+            };
+            let text = fonts.text(line, size);
+            let y = 80.0 + i as f32 * (size + 6.0) + if i > 0 { 10.0 } else { 0.0 };
+            renderer.text(&text, Point2::new(VIRTUAL_WIDTH / 2.0 - 200.0, y), color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::{Entrant, Format, Tournament};
+
+    fn entrants(n: usize) -> Vec<Entrant> {
+        (0..n)
+            .map(|i| Entrant {
+                name: format!("P{}", i),
+                computer: None,
+            })
+            .collect()
+    }
+
+    // Plays every match, the first player always winning 10 to `loser`.
+    fn play(tournament: &mut Tournament, loser: u32) -> usize {
+        let mut played = 0;
+        while let Some((round, index)) = tournament.next() {
+            tournament.report(round, index, [10, loser]);
+            played += 1;
+        }
+        played
+    }
+
+    #[test]
+    fn eliminates_down_to_a_champion_with_byes() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut tournament = Tournament::new(Format::SingleElimination, entrants(6), &mut rng);
+        let sizes: Vec<usize> = tournament.rounds.iter().map(Vec::len).collect();
+        assert_eq!(sizes, [4, 2, 1]);
+        assert_eq!(tournament.champion(), None);
+        // Two byes, then 5 matches for 6 players.
+        assert_eq!(play(&mut tournament, 4), 5);
+        let winner = tournament.rounds[2][0].winner;
+        assert!(winner.is_some());
+        assert_eq!(tournament.champion(), winner);
+    }
+
+    #[test]
+    fn everyone_plays_everyone_in_a_round_robin() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut tournament = Tournament::new(Format::RoundRobin, entrants(5), &mut rng);
+        assert_eq!(tournament.rounds.len(), 5);
+        assert_eq!(play(&mut tournament, 7), 10);
+        let mut pairs: Vec<[usize; 2]> = tournament
+            .rounds
+            .iter()
+            .flatten()
+            .map(|m| {
+                let [a, b] = [m.players[0].unwrap(), m.players[1].unwrap()];
+                [a.min(b), a.max(b)]
+            })
+            .collect();
+        pairs.sort_unstable();
+        pairs.dedup();
+        assert_eq!(pairs.len(), 10);
+        let standings = tournament.standings();
+        assert_eq!(standings.iter().map(|s| s.1).sum::<u32>(), 10);
+        assert_eq!(tournament.champion(), Some(standings[0].0));
+    }
+
+    #[test]
+    fn resumes_from_its_saved_form() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut tournament = Tournament::new(Format::SingleElimination, entrants(4), &mut rng);
+        tournament.report(0, 0, [3, 10]);
+        let saved = serde_json::to_string(&tournament).unwrap();
+        let resumed: Tournament = serde_json::from_str(&saved).unwrap();
+        assert_eq!(resumed, tournament);
+        assert_eq!(resumed.next(), Some((0, 1)));
+    }
+}